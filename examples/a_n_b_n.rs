@@ -55,5 +55,19 @@ fn main() {
             break;
         }
     }
-    println!("{}", runner.ir())
+    println!("{}", runner.ir());
+
+    // Compare with the language a^n b^n, n >= 1, on every input up to 10 symbols.
+    let is_a_n_b_n = |s: &str| {
+        let n = s.len() / 2;
+        n > 0
+            && s.len() == 2 * n
+            && s[..n].chars().all(|c| c == 'a')
+            && s[n..].chars().all(|c| c == 'b')
+    };
+    let counterexamples = verify::exhaustive(&tm, 10, 1000, is_a_n_b_n);
+    println!("{} counterexamples", counterexamples.len());
+    for c in counterexamples {
+        println!("{:?} on {:?}", c.mismatch, c.input);
+    }
 }
@@ -0,0 +1,27 @@
+StateSet = {q0, q1, q2, q3, q4}
+SymbolSet = {a, b}
+TSymbolSet = {B, a, b, x, y}
+FnSet = {
+    (q0, a) -> (q1, x, R),
+    (q0, y) -> (q3, y, R),
+    (q1, a) -> (q1, a, R),
+    (q1, b) -> (q2, y, L),
+    (q1, y) -> (q1, y, R),
+    (q2, a) -> (q2, a, L),
+    (q2, x) -> (q0, x, R),
+    (q2, y) -> (q2, y, L),
+    (q3, B) -> (q4, B, L),
+    (q3, y) -> (q3, y, R)
+}
+Start = q0
+FinalSet = {q4}
+Empty = B
+Allow = {W005}
+Tests = {
+    "ab" -> accept,
+    "aabb" -> accept,
+    "aab" -> reject,
+    "abb" -> reject,
+    "ba" -> reject,
+    "aaabbb" => "xxxyyy" within 100
+}
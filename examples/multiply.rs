@@ -86,19 +86,26 @@ pub fn tm_multiply() -> TM {
         .unwrap()
 }
 
-fn main() {
-    let tm = tm_multiply();
-    let mut n1 = String::new();
-    let mut n2 = String::new();
-    print!("INPUT n1.\n>>");
-    stdout().flush().unwrap();
-    stdin().read_line(&mut n1).unwrap();
-    print!("INPUT n2.\n>>");
+/// Prompt for a number, exiting if stdin has none.
+fn read_num(name: &str) -> u8 {
+    let mut line = String::new();
+    print!("INPUT {}.\n>>", name);
     stdout().flush().unwrap();
-    stdin().read_line(&mut n2).unwrap();
+    stdin().read_line(&mut line).unwrap();
+    line.trim().parse().unwrap_or_else(|_| {
+        eprintln!(
+            "{} must be a number from 0 to 255, got {:?}",
+            name,
+            line.trim()
+        );
+        std::process::exit(1)
+    })
+}
 
-    let mut n1: u8 = n1.trim().parse().unwrap();
-    let mut n2: u8 = n2.trim().parse().unwrap();
+fn main() {
+    let tm = tm_multiply();
+    let mut n1 = read_num("n1");
+    let mut n2 = read_num("n2");
     if n2 == 0 {
         std::mem::swap(&mut n1, &mut n2);
     }
@@ -0,0 +1,22 @@
+use turing_machine::compose::seq;
+use turing_machine::stdlib::*;
+use turing_machine::*;
+
+/// Copy a block and compare the copy with the original, which always
+/// accepts.
+fn main() {
+    let a = Alphabet::new("01", "xy", 'B').unwrap();
+    let tm = seq(&copy_block(&a), &compare_blocks(&a)).unwrap();
+    let mut runner = Runner::with_tm(&tm);
+    for input in ["", "0", "0110", "1101001"].iter() {
+        runner.feed_str(input);
+        let outcome = runner.run(10_000);
+        println!(
+            "{:>10} -> {:?} in {} steps: {}",
+            input,
+            outcome.verdict,
+            outcome.steps,
+            runner.ir().tape_str().trim_matches('B')
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! `run --inputs`: one machine on every line of a file.
+use super::{load_tm_for, print_json, read_file, Error, Output, ACCEPT, TIMEOUT};
+use std::fs;
+use std::path::Path;
+use turing_machine::*;
+
+#[derive(serde::Serialize)]
+struct Row<'a> {
+    input: &'a str,
+    verdict: Verdict,
+    steps: u64,
+    /// Tape cells spanned at the end of the run.
+    space: usize,
+}
+
+/// Step limit of each line when none is given, so that a machine looping on
+/// one line does not hold up the others.
+pub const DEFAULT_MAX_STEPS: u64 = 10_000;
+
+/// Run the machine in `file` on every line of `inputs` on one runner, lines
+/// starting with `#` are skipped. Exits with [`TIMEOUT`] if any input ran out
+/// of steps.
+pub fn run(
+    file: &Path,
+    inputs: &Path,
+    limit: u64,
+    output: Output,
+    report: Option<&Path>,
+) -> Result<i32, Error> {
+    let content = read_file(inputs)?;
+    let inputs = content
+        .lines()
+        .filter(|l| !l.starts_with('#'))
+        .map(|l| l.trim_end_matches('\r'))
+        .collect::<Vec<_>>();
+    let tm = load_tm_for(file, &inputs)?;
+    let mut runner = Runner::with_tm(&tm);
+    let mut rows = Vec::new();
+    for input in inputs {
+        runner.feed_str(input);
+        let outcome = runner.run(limit);
+        rows.push(Row {
+            input,
+            verdict: outcome.verdict,
+            steps: outcome.steps,
+            space: runner.tape().runs().iter().map(|(_, n)| n).sum(),
+        });
+    }
+
+    match output {
+        Output::Text => print_table(&rows),
+        Output::Json => print_json(&rows),
+    }
+    if let Some(path) = report {
+        let content = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::to_string_pretty(&rows).unwrap() + "\n",
+            _ => to_csv(&rows),
+        };
+        fs::write(path, content).map_err(|e| Error::Write(path.display().to_string(), e))?;
+    }
+    Ok(if rows.iter().any(|r| r.verdict == Verdict::Timeout) {
+        TIMEOUT
+    } else {
+        ACCEPT
+    })
+}
+
+fn print_table(rows: &[Row<'_>]) {
+    let input = |r: &Row<'_>| {
+        if r.input.is_empty() {
+            "ε".to_string()
+        } else {
+            r.input.to_string()
+        }
+    };
+    let width = rows
+        .iter()
+        .map(|r| input(r).chars().count())
+        .chain(Some("input".len()))
+        .max()
+        .unwrap();
+    println!(
+        "{:<width$}  {:<7}  {:>10}  {:>8}",
+        "input",
+        "verdict",
+        "steps",
+        "space",
+        width = width
+    );
+    for r in rows {
+        println!(
+            "{:<width$}  {:<7}  {:>10}  {:>8}",
+            input(r),
+            format!("{:?}", r.verdict),
+            r.steps,
+            r.space,
+            width = width
+        );
+    }
+    let count = |v: Verdict| rows.iter().filter(|r| r.verdict == v).count();
+    println!(
+        "{} inputs: {} accepted, {} rejected, {} timed out",
+        rows.len(),
+        count(Verdict::Accept),
+        count(Verdict::Reject),
+        count(Verdict::Timeout)
+    );
+}
+
+fn to_csv(rows: &[Row<'_>]) -> String {
+    let mut out = String::from("input,verdict,steps,space\n");
+    for r in rows {
+        let input = if r.input.contains([',', '"']) {
+            format!("\"{}\"", r.input.replace('"', "\"\""))
+        } else {
+            r.input.to_string()
+        };
+        out += &format!("{},{:?},{},{}\n", input, r.verdict, r.steps, r.space);
+    }
+    out
+}
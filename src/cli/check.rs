@@ -0,0 +1,71 @@
+//! `check`: validate a machine definition and print its lint warnings.
+use super::{load_document, print_json, Error, Output, ACCEPT, INVALID};
+use std::path::PathBuf;
+use turing_machine::lint::Warning;
+
+#[derive(Debug, clap::Args)]
+pub struct CheckArgs {
+    /// Machine definition, the format follows the extension.
+    file: PathBuf,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    file: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    states: usize,
+    syms: usize,
+    tape_syms: usize,
+    transfer_fns: usize,
+    warnings: Vec<Warning>,
+}
+
+pub fn check(args: CheckArgs) -> Result<i32, Error> {
+    let file = args.file.display().to_string();
+    let doc = match (load_document(&args.file), args.output) {
+        (Ok(doc), _) => doc,
+        (Err(e @ Error::Definition(..)), Output::Json) => {
+            print_json(&Report {
+                file,
+                ok: false,
+                error: Some(e.to_string()),
+                states: 0,
+                syms: 0,
+                tape_syms: 0,
+                transfer_fns: 0,
+                warnings: Vec::new(),
+            });
+            return Ok(INVALID);
+        }
+        (Err(e), _) => return Err(e),
+    };
+
+    let tm = &doc.tm;
+    let report = Report {
+        file,
+        ok: true,
+        error: None,
+        states: tm.states().count(),
+        syms: tm.syms().count(),
+        tape_syms: tm.tape_syms().count(),
+        transfer_fns: tm.transfer_fns().count(),
+        warnings: doc.lint(),
+    };
+    match args.output {
+        Output::Text => {
+            println!(
+                "{}: ok, {} states, {} symbols, {} tape symbols, {} transitions",
+                report.file, report.states, report.syms, report.tape_syms, report.transfer_fns
+            );
+            for w in report.warnings.iter() {
+                println!("warning {}", w);
+            }
+        }
+        Output::Json => print_json(&report),
+    }
+    Ok(ACCEPT)
+}
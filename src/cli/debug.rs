@@ -0,0 +1,294 @@
+//! Terminal debugger: step through a run, rewind, and stop on breakpoints.
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, Write};
+use turing_machine::*;
+
+/// Transitions taken by one `continue` before giving the control back.
+const CONTINUE_LIMIT: u64 = 1_000_000;
+
+/// Configurations kept in the history panel.
+const HISTORY_LEN: usize = 1000;
+
+const HELP: &str = "s/→ step  b/← back  c run to breakpoint  g go to halt  \
+                    t toggle breakpoint  r restart  ↑/↓ scroll  q quit";
+
+/// Raw mode on the alternate screen, restored on drop.
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Debugger<'a> {
+    name: &'a str,
+    input: &'a str,
+    runner: Runner<'a>,
+    breakpoints: BTreeSet<State>,
+    /// Step and `IR` of the latest configurations.
+    history: VecDeque<(u64, String)>,
+    /// History lines hidden below the panel.
+    scroll: usize,
+    message: String,
+}
+
+/// Debug `tm`, loaded from `name`, on `input` until the user quits.
+pub fn run(tm: &TM, name: &str, input: &str) -> io::Result<()> {
+    let mut debugger = Debugger {
+        name,
+        input,
+        runner: Runner::with_tm(tm),
+        breakpoints: BTreeSet::new(),
+        history: VecDeque::new(),
+        scroll: 0,
+        message: String::new(),
+    };
+    debugger.restart();
+
+    let _screen = Screen::enter()?;
+    let mut out = io::stdout();
+    loop {
+        debugger.draw(&mut out)?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        debugger.message.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Char('s') | KeyCode::Char(' ') | KeyCode::Right => debugger.step(),
+            KeyCode::Char('b') | KeyCode::Left => debugger.back(),
+            KeyCode::Char('c') => debugger.resume(true),
+            KeyCode::Char('g') => debugger.resume(false),
+            KeyCode::Char('t') => debugger.toggle_breakpoint(),
+            KeyCode::Char('r') => debugger.restart(),
+            KeyCode::Up => debugger.scroll += 1,
+            KeyCode::Down => debugger.scroll = debugger.scroll.saturating_sub(1),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl Debugger<'_> {
+    fn restart(&mut self) {
+        self.runner.feed_str(self.input);
+        self.history.clear();
+        self.scroll = 0;
+        self.record();
+    }
+
+    fn record(&mut self) {
+        let ir = self.runner.ir().to_string();
+        let config = ir.split_once('\n').map_or(&ir[..], |(_, c)| c);
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((self.runner.steps(), config.to_string()));
+    }
+
+    /// Take one transition, `false` once the machine has halted.
+    fn advance(&mut self) -> bool {
+        let steps = self.runner.steps();
+        if self.runner.step() != RunnerState::Running && self.runner.steps() == steps {
+            self.message = format!("machine halted: {:?}", self.runner.runner_state());
+            return false;
+        }
+        self.record();
+        true
+    }
+
+    fn step(&mut self) {
+        self.advance();
+    }
+
+    /// Run to the first breakpoint, or to the end with `stop == false`.
+    fn resume(&mut self, stop: bool) {
+        for _ in 0..CONTINUE_LIMIT {
+            if !self.advance() {
+                return;
+            }
+            if stop && self.breakpoints.contains(self.runner.current_state()) {
+                self.message = format!("breakpoint at {}", self.runner.current_state());
+                return;
+            }
+            if self.runner.runner_state() != RunnerState::Running {
+                self.message = format!("machine halted: {:?}", self.runner.runner_state());
+                return;
+            }
+        }
+        self.message = format!("paused after {} steps", CONTINUE_LIMIT);
+    }
+
+    /// Go back one step by running the input again.
+    fn back(&mut self) {
+        let steps = self.runner.steps();
+        if steps == 0 {
+            self.message = "at the first step".to_string();
+            return;
+        }
+        self.runner.feed_str(self.input);
+        while self.runner.steps() < steps - 1 {
+            self.runner.step();
+        }
+        if self.history.back().map(|(s, _)| *s) == Some(steps) {
+            self.history.pop_back();
+        }
+        if self.history.is_empty() {
+            self.record();
+        }
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let state = State::clone(self.runner.current_state());
+        if self.breakpoints.remove(&state) {
+            self.message = format!("breakpoint on {} removed", state);
+        } else {
+            self.message = format!("breakpoint on {} set", state);
+            self.breakpoints.insert(state);
+        }
+    }
+
+    /// The transition fired by the next step.
+    fn next_transition(&self) -> String {
+        let state = self.runner.current_state();
+        match self.runner.runner_state() {
+            RunnerState::Running => {}
+            s => return format!("none, machine halted: {:?}", s),
+        }
+        let sym = self.runner.tape().read();
+        match self.runner.tm().transfer(State::clone(state), sym) {
+            Some((next, write, dir)) => format!(
+                "({}, {}) → ({}, {}, {})",
+                state,
+                sym,
+                next,
+                write.unwrap_or(sym),
+                dir
+            ),
+            None => format!("none for ({}, {}), machine rejects", state, sym),
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        queue!(out, Clear(ClearType::All))?;
+
+        let title = format!("{} on \"{}\"", self.name, self.input);
+        queue!(
+            out,
+            MoveTo(0, 0),
+            SetAttribute(Attribute::Bold),
+            Print(clip(&title, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        let breakpoints = self
+            .breakpoints
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = format!(
+            "step {}  state {}  {:?}  breakpoints: {}",
+            self.runner.steps(),
+            self.runner.current_state(),
+            self.runner.runner_state(),
+            if breakpoints.is_empty() {
+                "none"
+            } else {
+                &breakpoints
+            }
+        );
+        queue!(out, MoveTo(0, 1), Print(clip(&status, width)))?;
+
+        // Tape window, two columns per cell with the head cell in the middle.
+        let tape = self.runner.ir().tape_str().chars().collect::<Vec<_>>();
+        let head = self.runner.tape().head() as i64;
+        let cells = (width / 2).max(1) as i64;
+        let first = head - cells / 2;
+        queue!(out, MoveTo(0, 3))?;
+        for i in first..first + cells {
+            let sym = match tape.get(i as usize) {
+                Some(s) if i >= 0 => *s,
+                _ => self.runner.tm().empty_sym(),
+            };
+            if i == head {
+                queue!(
+                    out,
+                    SetAttribute(Attribute::Reverse),
+                    Print(sym),
+                    SetAttribute(Attribute::Reset),
+                    Print(' ')
+                )?;
+            } else {
+                queue!(out, Print(sym), Print(' '))?;
+            }
+        }
+        let marker = format!("^ {}", self.runner.current_state());
+        queue!(
+            out,
+            MoveTo(((head - first) * 2) as u16, 4),
+            Print(clip(
+                &marker,
+                width.saturating_sub(((head - first) * 2) as usize)
+            ))
+        )?;
+
+        let next = format!("next: {}", self.next_transition());
+        queue!(out, MoveTo(0, 6), Print(clip(&next, width)))?;
+
+        // History panel, latest configuration at the bottom.
+        let rows = height.saturating_sub(11);
+        self.scroll = self.scroll.min(self.history.len().saturating_sub(rows));
+        let end = self.history.len() - self.scroll;
+        queue!(
+            out,
+            MoveTo(0, 8),
+            SetAttribute(Attribute::Bold),
+            Print("history"),
+            SetAttribute(Attribute::Reset)
+        )?;
+        for (row, (steps, config)) in self
+            .history
+            .range(end.saturating_sub(rows)..end)
+            .enumerate()
+        {
+            let line = format!("{:>8}  {}", steps, config);
+            queue!(out, MoveTo(0, 9 + row as u16), Print(clip(&line, width)))?;
+        }
+
+        let footer = height.saturating_sub(1) as u16;
+        queue!(
+            out,
+            MoveTo(0, footer.saturating_sub(1)),
+            Print(clip(&self.message, width)),
+            MoveTo(0, footer),
+            SetAttribute(Attribute::Dim),
+            Print(clip(HELP, width)),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()
+    }
+}
+
+fn clip(s: &str, width: usize) -> String {
+    s.chars().take(width).collect()
+}
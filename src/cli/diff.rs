@@ -0,0 +1,178 @@
+//! `diff`: compare the definitions of two machines and their verdicts on
+//! short inputs.
+use super::{exit_code, load_tm, print_json, Error, Output, ACCEPT, REJECT};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use turing_machine::verify::{equivalent, equivalent_outputs, Behaviour, Difference, Divergence};
+use turing_machine::*;
+
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Machine definitions, the format follows the extension.
+    left: PathBuf,
+    right: PathBuf,
+    /// Longest input compared, every word up to it is run.
+    #[arg(long, default_value_t = 8)]
+    max_len: usize,
+    /// Step limit of every run, running longer is reported as a timeout.
+    #[arg(long, default_value_t = 10_000)]
+    max_steps: u64,
+    /// Also compare the tapes left on accepted inputs, for transducers.
+    #[arg(long)]
+    outputs: bool,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    /// Lines of the definitions only in the left machine, prefixed with `-`,
+    /// or only in the right one, prefixed with `+`.
+    structure: Vec<String>,
+    max_len: usize,
+    divergence: Option<Divergence>,
+}
+
+/// Exits with 0 if the machines agree on every input, 1 if they differ and 2
+/// if they agree except on inputs some run timed out on.
+pub fn diff(args: DiffArgs) -> Result<i32, Error> {
+    let left = load_tm(&args.left)?;
+    let right = load_tm(&args.right)?;
+    let structure = structural_diff(&left, &right);
+    let divergence = if args.outputs {
+        equivalent_outputs(&left, &right, args.max_len, args.max_steps)
+    } else {
+        equivalent(&left, &right, args.max_len, args.max_steps)
+    };
+
+    let code = match &divergence {
+        None => ACCEPT,
+        Some(d) if d.difference == Difference::Timeout => exit_code(Verdict::Timeout),
+        Some(_) => REJECT,
+    };
+    match args.output {
+        Output::Text => {
+            println!("--- {}", args.left.display());
+            println!("+++ {}", args.right.display());
+            for line in structure.iter() {
+                println!("{}", line);
+            }
+            match &divergence {
+                None => println!(
+                    "same verdicts on every input up to {} symbols",
+                    args.max_len
+                ),
+                Some(d) => print_divergence(d),
+            }
+        }
+        Output::Json => print_json(&Report {
+            structure,
+            max_len: args.max_len,
+            divergence,
+        }),
+    }
+    Ok(code)
+}
+
+fn print_divergence(d: &Divergence) {
+    let what = match d.difference {
+        Difference::Verdict => "differ",
+        Difference::Output => "leave different tapes",
+        Difference::Timeout => "can not be compared",
+    };
+    println!("{} on {:?}:", what, d.input);
+    let print = |side: char, b: &Behaviour| {
+        println!(
+            "{} {:?} after {} steps, tape {:?}",
+            side, b.verdict, b.steps, b.output
+        )
+    };
+    print('-', &d.left);
+    print('+', &d.right);
+}
+
+/// Lines of the `.tm` definitions that differ, transitions keyed by their
+/// source state and symbol.
+fn structural_diff(left: &TM, right: &TM) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut header = |name: &str, l: String, r: String| {
+        if l != r {
+            lines.push(format!("- {} = {}", name, l));
+            lines.push(format!("+ {} = {}", name, r));
+        }
+    };
+    header(
+        "Start",
+        left.start_state().to_string(),
+        right.start_state().to_string(),
+    );
+    header(
+        "Empty",
+        left.empty_sym().to_string(),
+        right.empty_sym().to_string(),
+    );
+    let names = |states: &mut dyn Iterator<Item = &State>| states.map(|s| s.to_string()).collect();
+    let chars = |syms: &mut dyn Iterator<Item = Symbol>| syms.map(|s| s.to_string()).collect();
+    set_diff(
+        &mut lines,
+        "StateSet",
+        names(&mut left.states()),
+        names(&mut right.states()),
+    );
+    set_diff(
+        &mut lines,
+        "SymbolSet",
+        chars(&mut left.syms()),
+        chars(&mut right.syms()),
+    );
+    set_diff(
+        &mut lines,
+        "TSymbolSet",
+        chars(&mut left.tape_syms()),
+        chars(&mut right.tape_syms()),
+    );
+    set_diff(
+        &mut lines,
+        "FinalSet",
+        names(&mut left.accept_states()),
+        names(&mut right.accept_states()),
+    );
+
+    let mut fns: BTreeMap<(String, Symbol), (Option<String>, Option<String>)> = BTreeMap::new();
+    for (tm, is_left) in [(left, true), (right, false)].iter() {
+        for (s0, sym0, s1, sym1, dir) in tm.transfer_fns() {
+            let to = format!("({}, {}, {})", s1, sym1.unwrap_or(sym0), dir);
+            let entry = fns.entry((s0.to_string(), sym0)).or_default();
+            if *is_left {
+                entry.0 = Some(to);
+            } else {
+                entry.1 = Some(to);
+            }
+        }
+    }
+    for ((s0, sym0), (l, r)) in fns {
+        if l == r {
+            continue;
+        }
+        for (side, to) in [('-', l), ('+', r)] {
+            if let Some(to) = to {
+                lines.push(format!("{} ({}, {}) -> {}", side, s0, sym0, to));
+            }
+        }
+    }
+    lines
+}
+
+fn set_diff(lines: &mut Vec<String>, name: &str, left: Vec<String>, right: Vec<String>) {
+    for (side, from, other) in [('-', &left, &right), ('+', &right, &left)] {
+        let mut items = from
+            .iter()
+            .filter(|i| !other.contains(i))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !items.is_empty() {
+            items.sort();
+            lines.push(format!("{} {} {{{}}}", side, name, items.join(", ")));
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! `fmt` and `convert`: rewrite machine definitions.
+use super::{load_document, read_file, Error, ACCEPT, REJECT};
+use std::fs;
+use std::path::PathBuf;
+use turing_machine::formats::Format;
+
+#[derive(Debug, clap::Args)]
+pub struct FmtArgs {
+    /// Machine definition, the format follows the extension.
+    file: PathBuf,
+    /// Overwrite the file instead of printing it.
+    #[arg(short, long)]
+    write: bool,
+    /// Only tell whether the file is formatted, exit with 1 if not.
+    #[arg(long, conflicts_with = "write")]
+    check: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ConvertArgs {
+    /// Machine definition.
+    file: PathBuf,
+    /// Format of `file`, guessed from its extension by default.
+    #[arg(long)]
+    from: Option<Format>,
+    /// Output format: tm, bb, jff or lines. Guessed from `--out` by default.
+    #[arg(long)]
+    to: Option<Format>,
+    /// Write to this file instead of stdout.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+}
+
+pub fn fmt(args: FmtArgs) -> Result<i32, Error> {
+    let file = args.file.display().to_string();
+    let content = read_file(&args.file)?;
+    let format = Format::from_path(&args.file);
+    let doc = load_document(&args.file)?;
+    let has_comments = format
+        .has_comments(&content)
+        .map_err(|e| Error::Definition(file.clone(), e))?;
+    if has_comments {
+        return Err(Error::Usage(format!(
+            "{} has comments, formatting would drop them",
+            file
+        )));
+    }
+    let formatted = match format {
+        Format::Tm => doc.to_tm().map_err(Into::into),
+        _ => format.write(&doc.tm),
+    }
+    .map_err(|e| Error::Definition(file.clone(), e))?;
+
+    if args.check {
+        if formatted != content {
+            println!("{} is not formatted", file);
+            return Ok(REJECT);
+        }
+    } else if args.write {
+        if formatted != content {
+            fs::write(&args.file, formatted).map_err(|e| Error::Write(file, e))?;
+        }
+    } else {
+        print!("{}", formatted);
+    }
+    Ok(ACCEPT)
+}
+
+pub fn convert(args: ConvertArgs) -> Result<i32, Error> {
+    let file = args.file.display().to_string();
+    let from = args.from.unwrap_or_else(|| Format::from_path(&args.file));
+    let to = match (args.to, &args.out) {
+        (Some(to), _) => to,
+        (None, Some(out)) => Format::from_path(out),
+        (None, None) => return Err(Error::Usage("either --to or --out is needed".to_string())),
+    };
+    let tm = from
+        .parse(read_file(&args.file)?)
+        .map_err(|e| Error::Definition(file.clone(), e))?;
+    let converted = to.write(&tm).map_err(|e| Error::Definition(file, e))?;
+    match args.out {
+        Some(out) => {
+            fs::write(&out, converted).map_err(|e| Error::Write(out.display().to_string(), e))?
+        }
+        None => print!("{}", converted),
+    }
+    Ok(ACCEPT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MACHINE: &str = "StateSet = {q0, q1}
+SymbolSet = {a}
+FnSet = {(q0, a) -> (q1, a, R)}
+Start = q0
+FinalSet = {q1}
+";
+
+    /// Run `fmt --write` on a file holding `content`, returning the result
+    /// and the file afterwards.
+    fn fmt_write(name: &str, content: &str) -> (Result<i32, Error>, String) {
+        let file = std::env::temp_dir().join(format!("{}-{}.tm", name, std::process::id()));
+        fs::write(&file, content).unwrap();
+        let result = fmt(FmtArgs {
+            file: file.clone(),
+            write: true,
+            check: false,
+        });
+        let after = fs::read_to_string(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        (result, after)
+    }
+
+    #[test]
+    fn formats_a_file() {
+        let messy = MACHINE.replace(", ", ",").replace('\n', "\n\n");
+        let (result, after) = fmt_write("fmt-messy", &messy);
+        assert_eq!(result.unwrap(), ACCEPT);
+        assert_eq!(
+            after,
+            turing_machine::parse_document(MACHINE)
+                .unwrap()
+                .to_tm()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_a_commented_file() {
+        let commented = format!("/* accepts a */\n{}", MACHINE.replace(", ", " , "));
+        let (result, after) = fmt_write("fmt-commented", &commented);
+        assert!(matches!(result, Err(Error::Usage(_))), "{:?}", result);
+        assert_eq!(after, commented);
+    }
+}
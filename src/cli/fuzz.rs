@@ -0,0 +1,100 @@
+//! `fuzz`: look for inputs a machine does not halt on or disagrees with a
+//! reference machine on.
+use super::{load_tm, print_json, Error, Output, ACCEPT, REJECT};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use turing_machine::fuzz::{Failure, Fuzzer, Grammar, Strategy};
+use turing_machine::*;
+
+#[derive(Debug, clap::Args)]
+pub struct FuzzArgs {
+    /// Machine definition, the format follows the extension.
+    file: PathBuf,
+    /// Machine that must give the same verdicts, only halting is checked
+    /// without it.
+    #[arg(long)]
+    reference: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t)]
+    strategy: StrategyArg,
+    /// Grammar of the inputs, e.g. `S -> a<S>b | ab`, implies
+    /// `--strategy grammar`.
+    #[arg(long)]
+    grammar: Option<Grammar>,
+    #[arg(long, default_value_t = 1000)]
+    cases: usize,
+    #[arg(long, default_value_t = 16)]
+    max_len: usize,
+    /// Step limit of every case, running longer is a failure.
+    #[arg(long, default_value_t = 10_000)]
+    max_steps: u64,
+    /// Seed of the generator, random by default.
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StrategyArg {
+    Uniform,
+    #[default]
+    Length,
+    Grammar,
+}
+
+#[derive(serde::Serialize)]
+struct Report {
+    seed: u64,
+    cases: usize,
+    failure: Option<Failure>,
+}
+
+pub fn fuzz(args: FuzzArgs) -> Result<i32, Error> {
+    let tm = load_tm(&args.file)?;
+    let max_steps = args.max_steps;
+    let reference = args.reference.as_deref().map(load_tm).transpose()?;
+    let strategy = match (args.grammar, args.strategy) {
+        (Some(grammar), _) => Strategy::Grammar(grammar),
+        (None, StrategyArg::Grammar) => {
+            return Err(Error::Usage(
+                "--strategy grammar needs --grammar".to_string(),
+            ))
+        }
+        (None, StrategyArg::Uniform) => Strategy::Uniform,
+        (None, StrategyArg::Length) => Strategy::LengthBiased,
+    };
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+
+    let fuzzer = Fuzzer::new(&tm)
+        .strategy(strategy)
+        .cases(args.cases)
+        .max_len(args.max_len)
+        .step_limit(max_steps)
+        .seed(seed);
+    let result = match &reference {
+        Some(reference) => fuzzer.check(|input| {
+            let mut runner = Runner::with_tm(reference);
+            runner.feed_str(input);
+            runner.run(max_steps).verdict == Verdict::Accept
+        }),
+        None => fuzzer.check_halts(),
+    };
+
+    let failure = result.err();
+    match args.output {
+        Output::Text => match &failure {
+            Some(failure) => println!("{}", failure),
+            None => println!("no failure in {} cases (seed {})", args.cases, seed),
+        },
+        Output::Json => print_json(&Report {
+            seed,
+            cases: args.cases,
+            failure: failure.clone(),
+        }),
+    }
+    Ok(if failure.is_none() { ACCEPT } else { REJECT })
+}
@@ -0,0 +1,146 @@
+//! Commands of the `turing-machine` binary.
+pub mod batch;
+pub mod check;
+pub mod debug;
+pub mod diff;
+pub mod fmt;
+pub mod fuzz;
+pub mod repl;
+pub mod run;
+pub mod test;
+
+use std::fs::read_to_string;
+use std::io::{self, Read};
+use std::path::Path;
+use turing_machine::formats::{self, Format};
+use turing_machine::*;
+
+/// The machine accepted, or the command succeeded.
+pub const ACCEPT: i32 = 0;
+/// The machine rejected, or some check failed.
+pub const REJECT: i32 = 1;
+pub const TIMEOUT: i32 = 2;
+/// The machine definition could not be parsed.
+pub const INVALID: i32 = 3;
+pub const IO_ERROR: i32 = 4;
+pub const USAGE: i32 = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read {0}: {1}")]
+    Read(String, io::Error),
+    #[error("failed to write {0}: {1}")]
+    Write(String, io::Error),
+    #[error("invalid defination of turing machine in {0}\n{1}")]
+    Definition(String, formats::Error),
+    #[error("{0}")]
+    Usage(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Readline(#[from] rustyline::error::ReadlineError),
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Read(..) | Error::Write(..) | Error::Io(_) | Error::Readline(_) => IO_ERROR,
+            Error::Definition(..) => INVALID,
+            Error::Usage(_) => USAGE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+pub fn read_file(path: &Path) -> Result<String, Error> {
+    read_to_string(path).map_err(|e| Error::Read(path.display().to_string(), e))
+}
+
+/// Load a machine, the format follows the file extension.
+pub fn load_tm(path: &Path) -> Result<TM, Error> {
+    Format::from_path(path)
+        .parse(read_file(path)?)
+        .map_err(|e| Error::Definition(path.display().to_string(), e))
+}
+
+/// [`load_tm`] to run on `inputs`, their symbols are input symbols of the
+/// machine even if a format leaves them out, see [`Format::parse_with_syms`].
+pub fn load_tm_for(path: &Path, inputs: &[&str]) -> Result<TM, Error> {
+    let mut syms = inputs.iter().flat_map(|i| i.chars()).collect::<Vec<_>>();
+    syms.sort_unstable();
+    syms.dedup();
+    Format::from_path(path)
+        .parse_with_syms(read_file(path)?, &syms)
+        .map_err(|e| Error::Definition(path.display().to_string(), e))
+}
+
+/// Load a machine with its test cases, only `.tm` files have some.
+pub fn load_document(path: &Path) -> Result<Document, Error> {
+    let format = Format::from_path(path);
+    let content = read_file(path)?;
+    let doc = match format {
+        Format::Tm => parse_document(content).map_err(formats::Error::from),
+        _ => format.parse(content).map(|tm| Document {
+            tm,
+            tests: Vec::new(),
+            allow: Vec::new(),
+        }),
+    };
+    doc.map_err(|e| Error::Definition(path.display().to_string(), e))
+}
+
+/// The input word given as argument, read from `file`, or read from stdin
+/// when missing or `-`. One trailing line break is dropped.
+pub fn read_input(input: Option<&str>, file: Option<&Path>) -> Result<String, Error> {
+    let mut content = match (input, file) {
+        (Some(input), _) if input != "-" => return Ok(input.to_string()),
+        (_, Some(file)) => read_file(file)?,
+        _ => {
+            let mut content = String::new();
+            io::stdin()
+                .read_to_string(&mut content)
+                .map_err(|e| Error::Read("stdin".to_string(), e))?;
+            content
+        }
+    };
+    if content.ends_with('\n') {
+        content.pop();
+        if content.ends_with('\r') {
+            content.pop();
+        }
+    }
+    Ok(content)
+}
+
+pub fn exit_code(verdict: Verdict) -> i32 {
+    match verdict {
+        Verdict::Accept => ACCEPT,
+        Verdict::Reject => REJECT,
+        Verdict::Timeout => TIMEOUT,
+    }
+}
+
+/// Run up to `limit` transitions like [`Runner::run`], printing the
+/// configuration after every step.
+pub fn run_verbose(runner: &mut Runner<'_>, limit: u64) -> RunOutcome {
+    loop {
+        let steps = runner.steps();
+        let outcome = runner.run((steps + 1).min(limit));
+        if runner.steps() != steps {
+            println!("{}", runner.ir());
+        }
+        if outcome.verdict != Verdict::Timeout || runner.steps() >= limit {
+            return outcome;
+        }
+    }
+}
+
+pub fn print_json<T: serde::Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
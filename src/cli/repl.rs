@@ -0,0 +1,236 @@
+//! Line based REPL: load a machine once, then run inputs and edit it.
+use super::{load_tm, run_verbose, Error, ACCEPT};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::path::{Path, PathBuf};
+use turing_machine::*;
+
+const DEFAULT_MAX_STEPS: u64 = 100_000;
+
+const COMMANDS: &[&str] = &[
+    "run", "step", "show", "reset", "reload", "trace", "set", "define", "help", "quit",
+];
+
+const HELP: &str = "run <input>         feed input and run until halt or max-steps
+step [n]            take n transitions, 1 by default
+show                print the current configuration
+reset               feed the last input again
+reload              read the machine file again
+trace on|off        print every configuration while running
+set max-steps <n>   limit runs to n transitions
+define <fn>         add or replace a transition, e.g. (q0, a) -> (q1, x, R)
+help                print this message
+quit                leave";
+
+/// Completion of commands, then of the states of the loaded machine.
+struct ReplHelper {
+    states: Vec<String>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind([' ', '(', ',']).map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let words: Vec<String> = match line[..start].split_whitespace().next() {
+            None => COMMANDS.iter().map(|c| c.to_string()).collect(),
+            Some("trace") => vec!["on".to_string(), "off".to_string()],
+            Some("set") => vec!["max-steps".to_string()],
+            Some("define") => self.states.clone(),
+            _ => Vec::new(),
+        };
+        Ok((
+            start,
+            words.into_iter().filter(|w| w.starts_with(word)).collect(),
+        ))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+struct Settings {
+    path: PathBuf,
+    max_steps: u64,
+    trace: bool,
+    input: Option<String>,
+}
+
+/// What to do once a session on a machine ends.
+enum Next {
+    Quit,
+    Load(TM),
+}
+
+/// Start a REPL on the machine defined in `path`.
+pub fn run(path: &Path) -> Result<i32, Error> {
+    let mut tm = load_tm(path)?;
+    let mut editor = Editor::<ReplHelper, DefaultHistory>::new()?;
+    let mut settings = Settings {
+        path: path.to_path_buf(),
+        max_steps: DEFAULT_MAX_STEPS,
+        trace: false,
+        input: None,
+    };
+    loop {
+        let mut states = tm.states().map(|s| s.to_string()).collect::<Vec<_>>();
+        states.sort();
+        editor.set_helper(Some(ReplHelper { states }));
+        match session(&mut editor, &tm, &mut settings)? {
+            Next::Quit => return Ok(ACCEPT),
+            Next::Load(new) => tm = new,
+        }
+    }
+}
+
+/// Read commands against `tm` until it gets replaced or the user quits.
+fn session(
+    editor: &mut Editor<ReplHelper, DefaultHistory>,
+    tm: &TM,
+    settings: &mut Settings,
+) -> rustyline::Result<Next> {
+    let mut runner = Runner::with_tm(tm);
+    if let Some(input) = &settings.input {
+        runner.feed_str(input);
+    }
+    loop {
+        let line = match editor.readline("tm> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(Next::Quit),
+            Err(e) => return Err(e),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+        let (cmd, arg) = match line.split_once(char::is_whitespace) {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (line, ""),
+        };
+
+        match cmd {
+            "run" => {
+                settings.input = Some(arg.to_string());
+                runner.feed_str(arg);
+                if settings.trace {
+                    println!("{}", runner.ir());
+                }
+                let outcome = run_to(&mut runner, settings.max_steps, settings.trace);
+                print_outcome(&runner, outcome, settings.trace, false);
+            }
+            "step" => {
+                let n = match arg {
+                    "" => 1,
+                    n => match n.parse::<u64>() {
+                        Ok(n) => n,
+                        Err(_) => {
+                            eprintln!("error: invalid step count: {}", n);
+                            continue;
+                        }
+                    },
+                };
+                if runner.runner_state() == RunnerState::Hungry {
+                    eprintln!("error: no input, use run <input> first");
+                    continue;
+                }
+                let limit = runner.steps() + n;
+                let outcome = run_to(&mut runner, limit, settings.trace);
+                print_outcome(&runner, outcome, settings.trace, true);
+            }
+            "show" => println!("step {}: {}", runner.steps(), runner.ir()),
+            "reset" => match &settings.input {
+                Some(input) => {
+                    runner.feed_str(input);
+                    println!("{}", runner.ir());
+                }
+                None => eprintln!("error: no input, use run <input> first"),
+            },
+            "reload" => match load_tm(&settings.path) {
+                Ok(tm) => {
+                    println!("reloaded {}", settings.path.display());
+                    return Ok(Next::Load(tm));
+                }
+                Err(e) => eprintln!("error: {}", e),
+            },
+            "trace" => match arg {
+                "on" => settings.trace = true,
+                "off" => settings.trace = false,
+                _ => eprintln!("error: expected trace on|off"),
+            },
+            "set" => match arg.split_whitespace().collect::<Vec<_>>()[..] {
+                ["max-steps", n] => match n.parse() {
+                    Ok(n) => settings.max_steps = n,
+                    Err(_) => eprintln!("error: invalid step count: {}", n),
+                },
+                _ => eprintln!("error: expected set max-steps <n>"),
+            },
+            "define" => match define(tm, arg) {
+                Ok(tm) => {
+                    if settings.input.is_some() {
+                        println!("input fed again");
+                    }
+                    return Ok(Next::Load(tm));
+                }
+                Err(e) => eprintln!("error: {}", e),
+            },
+            "help" => println!("{}", HELP),
+            "quit" | "exit" => return Ok(Next::Quit),
+            _ => eprintln!("error: unknown command {}, try help", cmd),
+        }
+    }
+}
+
+/// Run up to `limit` transitions, printing every configuration with `trace`.
+fn run_to(runner: &mut Runner<'_>, limit: u64, trace: bool) -> RunOutcome {
+    if trace {
+        run_verbose(runner, limit)
+    } else {
+        runner.run(limit)
+    }
+}
+
+/// A run that timed out while `stepping` is only paused.
+fn print_outcome(runner: &Runner<'_>, outcome: RunOutcome, trace: bool, stepping: bool) {
+    if !trace {
+        println!("{}", runner.ir());
+    }
+    match outcome.verdict {
+        Verdict::Timeout if stepping => {
+            println!("running after {} steps", outcome.steps)
+        }
+        verdict => println!("{:?} after {} steps", verdict, outcome.steps),
+    }
+}
+
+/// `tm` with the transition `def` added, replacing the one it overlaps.
+fn define(tm: &TM, def: &str) -> Result<TM, String> {
+    let item = parse_transfer_fn(def).map_err(|e| e.to_string())?;
+    let (s0, s1) = item.states();
+    let (sym0, sym1) = item.syms();
+    tm.to_builder()
+        .states(vec![s0.to_string(), s1.to_string()])
+        .tape_syms(vec![sym0, sym1])
+        .transfer_fn(item)
+        .build()
+}
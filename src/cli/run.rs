@@ -0,0 +1,211 @@
+//! `run` and `trace`: a machine on one input.
+use super::{batch, exit_code, load_tm_for, print_json, read_input, run_verbose, Error, Output};
+use std::path::PathBuf;
+use turing_machine::*;
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    /// Machine definition, the format follows the extension.
+    file: PathBuf,
+    /// Input word, read from stdin if missing or `-`.
+    input: Option<String>,
+    /// Read the input word from a file.
+    #[arg(long, conflicts_with = "input")]
+    input_file: Option<PathBuf>,
+    /// Run every line of a file as an input and print a summary table.
+    #[arg(long, conflicts_with_all = ["input", "input_file", "verbose"])]
+    inputs: Option<PathBuf>,
+    /// Also write the results of `--inputs` to a file, JSON if it ends with
+    /// `.json` and CSV otherwise.
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Stop after this many transitions, by default 10000 for each line of
+    /// `--inputs` and no limit for a single input.
+    #[arg(long)]
+    max_steps: Option<u64>,
+    /// Print the configuration after every step.
+    #[arg(short, long)]
+    verbose: bool,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct TraceArgs {
+    /// Machine definition, the format follows the extension.
+    file: PathBuf,
+    /// Input word, read from stdin if missing or `-`.
+    input: Option<String>,
+    /// Read the input word from a file.
+    #[arg(long, conflicts_with = "input")]
+    input_file: Option<PathBuf>,
+    /// Stop after this many transitions.
+    #[arg(long, default_value_t = 10_000)]
+    max_steps: u64,
+    #[arg(long, value_enum, default_value_t)]
+    notation: NotationArg,
+    /// Drop empty symbols on both ends of the tape.
+    #[arg(long)]
+    trim: bool,
+    /// Break lines to keep them within this many characters.
+    #[arg(long)]
+    wrap: Option<usize>,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NotationArg {
+    #[default]
+    Plain,
+    Unicode,
+    Latex,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    input: &'a str,
+    #[serde(flatten)]
+    outcome: RunOutcome,
+    config: Config,
+}
+
+pub fn run(args: RunArgs) -> Result<i32, Error> {
+    if args.report.is_some() && args.inputs.is_none() {
+        return Err(Error::Usage("--report needs --inputs".to_string()));
+    }
+    if let Some(inputs) = &args.inputs {
+        let limit = args.max_steps.unwrap_or(batch::DEFAULT_MAX_STEPS);
+        return batch::run(
+            &args.file,
+            inputs,
+            limit,
+            args.output,
+            args.report.as_deref(),
+        );
+    }
+    let limit = args.max_steps.unwrap_or(u64::MAX);
+    let input = read_input(args.input.as_deref(), args.input_file.as_deref())?;
+    let tm = load_tm_for(&args.file, &[&input])?;
+
+    let mut runner = Runner::with_tm(&tm);
+    runner.feed_str(&input);
+    let outcome = if args.verbose && args.output == Output::Text {
+        println!("{}", runner.ir());
+        run_verbose(&mut runner, limit)
+    } else {
+        runner.run(limit)
+    };
+    match args.output {
+        Output::Text => {
+            if !args.verbose {
+                println!("{}", runner.ir());
+            }
+            println!("{:?} after {} steps", outcome.verdict, outcome.steps);
+        }
+        Output::Json => print_json(&Report {
+            input: &input,
+            outcome,
+            config: runner.config(),
+        }),
+    }
+    Ok(exit_code(outcome.verdict))
+}
+
+pub fn trace(args: TraceArgs) -> Result<i32, Error> {
+    let input = read_input(args.input.as_deref(), args.input_file.as_deref())?;
+    let tm = load_tm_for(&args.file, &[&input])?;
+    let trace = Runner::with_tm(&tm).trace(&input, args.max_steps);
+    match args.output {
+        Output::Text => {
+            let notation = match args.notation {
+                NotationArg::Plain => Notation::Plain,
+                NotationArg::Unicode => Notation::Unicode,
+                NotationArg::Latex => Notation::Latex,
+            };
+            let mut history = History::new(&trace, tm.empty_sym())
+                .notation(notation)
+                .trim_blanks(args.trim);
+            if let Some(width) = args.wrap {
+                history = history.wrap(width);
+            }
+            println!("{}", history);
+        }
+        Output::Json => print_json(&trace),
+    }
+    Ok(exit_code(trace.outcome.verdict))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::TIMEOUT;
+    use std::fs;
+
+    #[test]
+    fn batch_times_out_looping_lines() {
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let file = dir.join(format!("batch-loop-{}.tm", id));
+        let inputs = dir.join(format!("batch-loop-{}.txt", id));
+        let report = dir.join(format!("batch-loop-{}.json", id));
+        // Accepts a word starting with `b` and loops on the others.
+        let tm = "StateSet = {q0, q1}
+            SymbolSet = {a, b}
+            FnSet = {(q0, a) -> (q0, a, S), (q0, b) -> (q1, b, R)}
+            Start = q0
+            FinalSet = {q1}";
+        fs::write(&file, tm).unwrap();
+        fs::write(&inputs, "b\na\nba\n").unwrap();
+
+        let code = run(RunArgs {
+            file: file.clone(),
+            input: None,
+            input_file: None,
+            inputs: Some(inputs.clone()),
+            report: Some(report.clone()),
+            max_steps: None,
+            verbose: false,
+            output: Output::Text,
+        });
+        let rows: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&report).unwrap()).unwrap();
+        for path in [file, inputs, report] {
+            fs::remove_file(path).unwrap();
+        }
+
+        assert_eq!(code.unwrap(), TIMEOUT);
+        let verdicts = rows
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| (r["verdict"].as_str().unwrap(), r["steps"].as_u64().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verdicts,
+            vec![
+                ("Accept", 1),
+                ("Timeout", batch::DEFAULT_MAX_STEPS),
+                ("Accept", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn wildcards_cover_the_input() {
+        let file = std::env::temp_dir().join(format!("wildcards-{}.txt", std::process::id()));
+        fs::write(&file, "0 * * r 0\n0 _ _ * halt\n").unwrap();
+        let code = run(RunArgs {
+            file: file.clone(),
+            input: Some("ab".to_string()),
+            input_file: None,
+            inputs: None,
+            report: None,
+            max_steps: None,
+            verbose: false,
+            output: Output::Text,
+        });
+        fs::remove_file(file).unwrap();
+        assert_eq!(code.unwrap(), crate::cli::ACCEPT);
+    }
+}
@@ -0,0 +1,92 @@
+//! `test`: run the test cases of a machine.
+use super::{load_document, print_json, Error, Output, ACCEPT, REJECT};
+use std::path::PathBuf;
+use turing_machine::*;
+
+#[derive(Debug, clap::Args)]
+pub struct TestArgs {
+    /// Machine definition, the `Tests` section of a `.tm` file is run.
+    file: PathBuf,
+    /// Input the machine must accept, may be repeated.
+    #[arg(long)]
+    accept: Vec<String>,
+    /// Input the machine must reject, may be repeated.
+    #[arg(long)]
+    reject: Vec<String>,
+    /// Step limit of cases without their own, running longer fails the case.
+    #[arg(long, default_value_t = 10_000)]
+    max_steps: u64,
+    #[arg(long, value_enum, default_value_t)]
+    output: Output,
+}
+
+#[derive(serde::Serialize)]
+struct CaseReport {
+    #[serde(flatten)]
+    case: TestCase,
+    #[serde(flatten)]
+    result: TestResult,
+}
+
+pub fn test(args: TestArgs) -> Result<i32, Error> {
+    let Document { tm, tests, .. } = load_document(&args.file)?;
+    let cases = tests
+        .into_iter()
+        .chain(
+            args.accept
+                .into_iter()
+                .map(|input| TestCase::new(input, Expect::Verdict(Verdict::Accept))),
+        )
+        .chain(
+            args.reject
+                .into_iter()
+                .map(|input| TestCase::new(input, Expect::Verdict(Verdict::Reject))),
+        );
+    let max_steps = args.max_steps;
+    let reports = cases
+        .map(|case| CaseReport {
+            result: case.run(&tm, max_steps),
+            case,
+        })
+        .collect::<Vec<_>>();
+
+    let failed = reports.iter().filter(|r| !r.result.passed).count();
+    match args.output {
+        Output::Text => {
+            for r in reports.iter() {
+                print_report(r);
+            }
+            println!("{} passed, {} failed", reports.len() - failed, failed);
+        }
+        Output::Json => print_json(&reports),
+    }
+    Ok(if failed == 0 { ACCEPT } else { REJECT })
+}
+
+fn print_report(r: &CaseReport) {
+    let expected = match &r.case.expect {
+        Expect::Verdict(verdict) => format!("{:?}", verdict),
+        Expect::Output(output) => format!("Accept with {:?}", output),
+    };
+    let got = match &r.case.expect {
+        Expect::Output(_) => format!("{:?} with {:?}", r.result.outcome.verdict, r.result.output),
+        Expect::Verdict(_) => format!("{:?}", r.result.outcome.verdict),
+    };
+    if r.result.passed {
+        println!("ok   {:?}: {}", r.case.input, expected);
+        return;
+    }
+    println!(
+        "FAIL {:?}: expected {}, got {} after {} steps",
+        r.case.input, expected, got, r.result.outcome.steps
+    );
+    for c in r.result.tail.iter() {
+        let (u, v) = c.tape.split_at(
+            c.tape
+                .char_indices()
+                .nth(c.head)
+                .map_or(c.tape.len(), |(i, _)| i),
+        );
+        println!("    {:>8}  {}<{}>{}", c.steps, u, c.state, v);
+    }
+}
@@ -0,0 +1,270 @@
+//! Machines assembled from smaller ones.
+//!
+//! A machine halts as soon as it enters an accept state, and rejects when it
+//! has no transition. The combinators keep the tape and the head where a part
+//! halted and go on with the next part:
+//! - a transition into an accept state of a part leads to the start state of
+//!   the next part instead, so going on after an accept takes no step;
+//! - a missing transition of a part becomes a transition to the start state
+//!   of the next part that moves nowhere, so going on after a reject takes
+//!   one step.
+//!
+//! States are renamed apart by prefixing them with `a`, `b` and `c`, in the
+//! order the parts are given. Parts must share the empty symbol, the input
+//! symbols are the ones of the first part.
+//!
+//! A part starting in an accept state still runs the transitions out of it,
+//! so it is entered through a state `start-a`, `start-b` or `start-c` that
+//! has these transitions and does not accept.
+use crate::tm::{HeadDirection, State, Symbol, TMBuilder, TransferFnItem, TM};
+
+use rustc_hash::FxHashSet;
+
+/// Run `a`, then `b` if `a` accepts. Accepts if `b` accepts, rejects if
+/// either rejects.
+pub fn seq(a: &TM, b: &TM) -> Result<TM, String> {
+    let mut c = Composition::new(&[a, b])?;
+    c.embed(a, "a", Some(start("b", b)), None);
+    c.embed(b, "b", None, None);
+    c.build(a)
+}
+
+/// Run `test`, then `on_accept` if it accepts or `on_reject` if it rejects.
+/// Accepts if the part run after `test` accepts.
+pub fn branch(test: &TM, on_accept: &TM, on_reject: &TM) -> Result<TM, String> {
+    let mut c = Composition::new(&[test, on_accept, on_reject])?;
+    c.embed(
+        test,
+        "a",
+        Some(start("b", on_accept)),
+        Some(start("c", on_reject)),
+    );
+    c.embed(on_accept, "b", None, None);
+    c.embed(on_reject, "c", None, None);
+    c.build(test)
+}
+
+/// Run `test`, and `body` then `test` again as long as `test` accepts.
+/// Accepts once `test` rejects, in a state named `done`, and rejects if
+/// `body` rejects.
+pub fn loop_while(test: &TM, body: &TM) -> Result<TM, String> {
+    let mut c = Composition::new(&[test, body])?;
+    c.embed(test, "a", Some(start("b", body)), Some(DONE.to_string()));
+    c.embed(body, "b", Some(start("a", test)), None);
+    c.states.insert(DONE.to_string());
+    c.accept_states.insert(DONE.to_string());
+    c.build(test)
+}
+
+/// Accept state of [`loop_while`], prefixed and entry states never clash
+/// with it.
+const DONE: &str = "done";
+
+/// State the part `tm` with `prefix` is entered through.
+fn start(prefix: &str, tm: &TM) -> String {
+    if tm.accept(tm.start_state()) {
+        format!("start-{}", prefix)
+    } else {
+        format!("{}{}", prefix, tm.start_state())
+    }
+}
+
+struct Composition {
+    tape_syms: Vec<Symbol>,
+    empty_sym: Symbol,
+    states: FxHashSet<String>,
+    accept_states: FxHashSet<String>,
+    fns: Vec<TransferFnItem>,
+}
+
+impl Composition {
+    fn new(parts: &[&TM]) -> Result<Self, String> {
+        let empty_sym = parts[0].empty_sym();
+        if parts.iter().any(|tm| tm.empty_sym() != empty_sym) {
+            return Err("Composed machines have different empty symbols".to_string());
+        }
+        let mut tape_syms = parts
+            .iter()
+            .flat_map(|tm| tm.tape_syms())
+            .collect::<FxHashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        tape_syms.sort_unstable();
+        Ok(Self {
+            tape_syms,
+            empty_sym,
+            states: FxHashSet::default(),
+            accept_states: FxHashSet::default(),
+            fns: Vec::new(),
+        })
+    }
+
+    /// Add the states and transitions of `tm` with `prefix`, going to the
+    /// state `on_accept` instead of accepting, and to `on_reject` instead of
+    /// rejecting. A missing state keeps the accept or reject. An accepting
+    /// start state also gets its entry state from [`start`].
+    fn embed(
+        &mut self,
+        tm: &TM,
+        prefix: &str,
+        on_accept: Option<String>,
+        on_reject: Option<String>,
+    ) {
+        let name = |s: &State| format!("{}{}", prefix, s);
+        let halts = |s: &State| on_accept.is_some() && tm.accept(s);
+        let target = |s: &State| match &on_accept {
+            Some(next) if tm.accept(s) => next.clone(),
+            _ => name(s),
+        };
+        let entry = start(prefix, tm);
+        let enters = |s: &State| tm.accept(s) && s == tm.start_state();
+        for s in tm.states().filter(|s| !halts(s)) {
+            self.states.insert(name(s));
+            if tm.accept(s) {
+                self.accept_states.insert(name(s));
+            }
+        }
+        self.states.insert(entry.clone());
+        for (s0, sym0, s1, sym1, dir) in tm.transfer_fns() {
+            let mut from = Vec::new();
+            if !halts(s0) {
+                from.push(name(s0));
+            }
+            if enters(s0) {
+                from.push(entry.clone());
+            }
+            for from in from {
+                self.fns.push(
+                    TransferFnItem::new()
+                        .from(from, sym0)
+                        .to(target(s1), sym1, dir),
+                );
+            }
+        }
+        if let Some(next) = &on_reject {
+            for s in tm.states() {
+                let from = if !tm.accept(s) {
+                    name(s)
+                } else if enters(s) {
+                    entry.clone()
+                } else {
+                    continue;
+                };
+                for sym in self.tape_syms.iter() {
+                    if tm.transfer(State::clone(s), *sym).is_none() {
+                        self.fns
+                            .push(TransferFnItem::new().from(from.clone(), *sym).to(
+                                next.clone(),
+                                None,
+                                HeadDirection::Stop,
+                            ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Build the machine starting at the start state of `first`.
+    fn build(self, first: &TM) -> Result<TM, String> {
+        TMBuilder::new()
+            .states(self.states)
+            .accept_states(self.accept_states)
+            .syms(first.syms())
+            .tape_syms(self.tape_syms)
+            .empty_sym(self.empty_sym)
+            .transfer_fns(self.fns)
+            .start_state(start("a", first))
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::run;
+    use crate::{parse, Verdict};
+
+    fn machine(start: &str, fns: &str, accept: &str) -> TM {
+        parse(format!(
+            "SymbolSet = {{0, 1}}
+            TSymbolSet = {{0, 1, x, B}}
+            FnSet = {{{}}}
+            Start = {}
+            FinalSet = {{{}}}",
+            fns, start, accept
+        ))
+        .unwrap()
+    }
+
+    /// Accepts `1` in two steps, entering its accepting start state again.
+    fn accepting_start() -> TM {
+        machine("q0", "(q0, 1) -> (q1, 1, R), (q1, B) -> (q0, B, L)", "q0")
+    }
+
+    /// Accepts at once, in one step.
+    fn id() -> TM {
+        machine(
+            "p0",
+            "(p0, 0) -> (pa, 0, S), (p0, 1) -> (pa, 1, S), (p0, B) -> (pa, B, S)",
+            "pa",
+        )
+    }
+
+    /// Accepts if the head reads `1`, rejects otherwise.
+    fn reads_one() -> TM {
+        machine("t0", "(t0, 1) -> (ta, 1, S)", "ta")
+    }
+
+    /// Writes `symbol` and moves right.
+    fn write(symbol: char) -> TM {
+        machine(
+            "w0",
+            &format!(
+                "(w0, 0) -> (wa, {0}, R), (w0, 1) -> (wa, {0}, R), (w0, B) -> (wa, {0}, R)",
+                symbol
+            ),
+            "wa",
+        )
+    }
+
+    #[test]
+    fn seq_runs_parts_with_accepting_start_states() {
+        let a = accepting_start();
+        assert_eq!(run(&a, "1"), (Verdict::Accept, 2, "1".to_string()));
+        assert_eq!(run(&a, "").0, Verdict::Reject);
+
+        let first = seq(&a, &id()).unwrap();
+        assert_eq!(run(&first, "1"), (Verdict::Accept, 3, "1".to_string()));
+        assert_eq!(run(&first, "").0, Verdict::Reject);
+        assert_eq!(run(&first, "0").0, Verdict::Reject);
+
+        let second = seq(&id(), &a).unwrap();
+        assert_eq!(run(&second, "1"), (Verdict::Accept, 3, "1".to_string()));
+        assert_eq!(run(&second, "").0, Verdict::Reject);
+    }
+
+    #[test]
+    fn branch_runs_the_part_for_the_verdict() {
+        let tm = branch(&reads_one(), &write('x'), &write('0')).unwrap();
+        assert_eq!(run(&tm, "1"), (Verdict::Accept, 2, "x".to_string()));
+        assert_eq!(run(&tm, "01"), (Verdict::Accept, 2, "01".to_string()));
+
+        let tm = branch(&reads_one(), &accepting_start(), &id()).unwrap();
+        assert_eq!(run(&tm, "1"), (Verdict::Accept, 3, "1".to_string()));
+        assert_eq!(run(&tm, "0"), (Verdict::Accept, 2, "0".to_string()));
+    }
+
+    #[test]
+    fn loop_while_runs_the_body_until_the_test_rejects() {
+        let tm = loop_while(&reads_one(), &write('x')).unwrap();
+        assert_eq!(run(&tm, "110"), (Verdict::Accept, 5, "xx0".to_string()));
+        assert!(tm.accept(DONE));
+
+        // The body accepts in its start state again, one cell further right.
+        let tm = loop_while(&reads_one(), &accepting_start()).unwrap();
+        assert_eq!(run(&tm, "1").0, Verdict::Timeout);
+        let tm = loop_while(&accepting_start(), &id()).unwrap();
+        assert_eq!(run(&tm, "1").0, Verdict::Timeout);
+        assert_eq!(run(&tm, "0"), (Verdict::Accept, 1, "0".to_string()));
+    }
+}
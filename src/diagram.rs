@@ -0,0 +1,397 @@
+use crate::tm::{HeadDirection, State, Symbol, TM};
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// Horizontal and vertical distance between states in TikZ, in cm.
+const TIKZ_SPACING: (f64, f64) = (3.0, 2.0);
+
+/// Transitions from a state to another, `(read, write, dir)` each.
+type Edge = (State, State, Vec<(Symbol, Symbol, HeadDirection)>);
+
+impl TM {
+    /// State diagram in Graphviz DOT.
+    ///
+    /// The start state gets an incoming arrow, accept states and reject
+    /// states (no outgoing transition) are double circles, the latter in red.
+    /// Transitions between the same states share one edge, labels are joined
+    /// with `|`.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        out += "digraph tm {\n";
+        out += "    rankdir=LR;\n";
+        out += "    node [shape=circle];\n";
+        out += "    __start [shape=point];\n";
+        writeln!(out, "    __start -> {};", quote(self.start_state())).unwrap();
+        for s in sorted_states(self) {
+            if self.accept(&s) {
+                writeln!(out, "    {} [shape=doublecircle];", quote(&s)).unwrap();
+            } else if is_reject(self, &s) {
+                writeln!(out, "    {} [shape=doublecircle, color=red];", quote(&s)).unwrap();
+            }
+        }
+        for (from, to, labels) in edges(self) {
+            writeln!(
+                out,
+                "    {} -> {} [label={}];",
+                quote(&from),
+                quote(&to),
+                quote(&join_labels(&labels, label, " | "))
+            )
+            .unwrap();
+        }
+        out += "}\n";
+        out
+    }
+
+    /// State diagram as a Mermaid `stateDiagram-v2`.
+    ///
+    /// States are declared in breadth-first order from the start state, accept
+    /// states lead to `[*]` and reject states get the `reject` class.
+    pub fn to_mermaid(&self) -> String {
+        let ids = bfs_states(self)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (s, _))| (s, format!("s{}", i)))
+            .collect::<Vec<_>>();
+        let id = ids
+            .iter()
+            .map(|(s, id)| (&s[..], id.as_str()))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut out = String::new();
+        out += "stateDiagram-v2\n";
+        out += "    direction LR\n";
+        out += "    classDef reject stroke:red\n";
+        for (s, i) in ids.iter() {
+            writeln!(out, "    state \"{}\" as {}", s.replace('"', "#quot;"), i).unwrap();
+        }
+        writeln!(out, "    [*] --> {}", id[&self.start_state()[..]]).unwrap();
+        for (s, i) in ids.iter() {
+            if self.accept(s) {
+                writeln!(out, "    {} --> [*]", i).unwrap();
+            } else if is_reject(self, s) {
+                writeln!(out, "    class {} reject", i).unwrap();
+            }
+        }
+        for (from, to, labels) in edges(self) {
+            let labels = join_labels(&labels, label, " | ").replace(':', "#58;");
+            writeln!(
+                out,
+                "    {} --> {} : {}",
+                id[&from[..]],
+                id[&to[..]],
+                labels
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// State diagram as a `tikzpicture` using the `automata` library.
+    ///
+    /// States are placed column by column in breadth-first order from the
+    /// start state, so the output does not depend on hashing.
+    pub fn to_tikz(&self) -> String {
+        let mut ids = FxHashMap::default();
+        let mut column = Vec::new();
+        let mut out = String::new();
+        out += "% \\usetikzlibrary{automata, arrows}\n";
+        out += "\\begin{tikzpicture}[->, >=stealth, auto, semithick]\n";
+        for (i, (s, depth)) in bfs_states(self).into_iter().enumerate() {
+            column.resize(column.len().max(depth + 1), 0);
+            let x = depth as f64 * TIKZ_SPACING.0;
+            let y = 0.0 - column[depth] as f64 * TIKZ_SPACING.1;
+            column[depth] += 1;
+
+            let mut style = vec!["state"];
+            if s == *self.start_state() {
+                style.push("initial");
+            }
+            if self.accept(&s) {
+                style.push("accepting");
+            } else if is_reject(self, &s) {
+                style.push("draw=red");
+            }
+            writeln!(
+                out,
+                "    \\node[{}] (s{}) at ({:.1}, {:.1}) {{{}}};",
+                style.join(", "),
+                i,
+                x,
+                y,
+                tex_escape(&s)
+            )
+            .unwrap();
+            ids.insert(s, i);
+        }
+
+        out += "    \\path\n";
+        for (from, to, labels) in edges(self) {
+            let (a, b) = (ids[&from], ids[&to]);
+            let style = if a == b {
+                "[loop above] "
+            } else if self
+                .transfer_fns()
+                .any(|(f, _, t, _, _)| *f == to && *t == from)
+            {
+                "[bend left] "
+            } else {
+                ""
+            };
+            let labels = join_labels(&labels, tex_label, " $|$ ");
+            writeln!(
+                out,
+                "        (s{}) edge {}node {{{}}} (s{})",
+                a, style, labels, b
+            )
+            .unwrap();
+        }
+        out += "    ;\n";
+        out += "\\end{tikzpicture}\n";
+        out
+    }
+}
+
+/// States with their distance from the start state, in breadth-first order.
+/// Unreachable states follow in name order, one column after the others.
+fn bfs_states(tm: &TM) -> Vec<(State, usize)> {
+    let edges = edges(tm);
+    let mut depth = FxHashMap::default();
+    let mut order = Vec::new();
+    let mut queue = VecDeque::new();
+    depth.insert(State::clone(tm.start_state()), 0);
+    queue.push_back(State::clone(tm.start_state()));
+    while let Some(s) = queue.pop_front() {
+        let d = depth[&s];
+        for (_, to, _) in edges.iter().filter(|(from, _, _)| *from == s) {
+            if !depth.contains_key(to) {
+                depth.insert(State::clone(to), d + 1);
+                queue.push_back(State::clone(to));
+            }
+        }
+        order.push((s, d));
+    }
+    let next = order.iter().map(|(_, d)| d + 1).max().unwrap_or(0);
+    for s in sorted_states(tm) {
+        if !depth.contains_key(&s) {
+            order.push((s, next));
+        }
+    }
+    order
+}
+
+/// States, start state first and the others in name order.
+fn sorted_states(tm: &TM) -> Vec<State> {
+    let mut states = tm
+        .states()
+        .filter(|s| *s != tm.start_state())
+        .cloned()
+        .collect::<Vec<_>>();
+    states.sort();
+    states.insert(0, State::clone(tm.start_state()));
+    states
+}
+
+/// A non-accept state without outgoing transitions rejects every input.
+fn is_reject(tm: &TM, s: &str) -> bool {
+    !tm.accept(s) && tm.transfer_fns().all(|(from, _, _, _, _)| &from[..] != s)
+}
+
+/// Transitions grouped by source and target, in name order.
+fn edges(tm: &TM) -> Vec<Edge> {
+    let mut fns = tm.transfer_fns().collect::<Vec<_>>();
+    fns.sort_by(|a, b| (a.0, a.2, a.1).cmp(&(b.0, b.2, b.1)));
+
+    let mut edges: Vec<Edge> = Vec::new();
+    for (from, sym, to, write, dir) in fns {
+        let label = (sym, write.unwrap_or(sym), dir);
+        match edges.last_mut() {
+            Some((f, t, labels)) if f == from && t == to => labels.push(label),
+            _ => edges.push((State::clone(from), State::clone(to), vec![label])),
+        }
+    }
+    edges
+}
+
+fn join_labels<F>(labels: &[(Symbol, Symbol, HeadDirection)], f: F, sep: &str) -> String
+where
+    F: Fn(Symbol, Symbol, HeadDirection) -> String,
+{
+    labels
+        .iter()
+        .map(|(read, write, dir)| f(*read, *write, *dir))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+/// `a→x,R`.
+fn label(read: Symbol, write: Symbol, dir: HeadDirection) -> String {
+    format!("{}→{},{}", read, write, dir)
+}
+
+fn tex_label(read: Symbol, write: Symbol, dir: HeadDirection) -> String {
+    format!(
+        "{}$\\to${},{}",
+        tex_escape(&read.to_string()),
+        tex_escape(&write.to_string()),
+        dir
+    )
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn tex_escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => out += "\\textbackslash{}",
+            '~' => out += "\\textasciitilde{}",
+            '^' => out += "\\textasciicircum{}",
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, tm_bar, TM};
+
+    /// `tm_bar` with `q2` going back to `q1` on `b`, an unreachable `q5` and
+    /// an unreachable reject state `q6`.
+    fn tm_bar_extended() -> TM {
+        parse(
+            "StateSet = {q0, q1, q2, q3, q4, q5, q6}
+            SymbolSet = {a, b}
+            TSymbolSet = {B, a, b, x, y}
+            FnSet = {
+                (q0, a) -> (q1, x, R), (q0, y) -> (q3, y, R),
+                (q1, a) -> (q1, a, R), (q1, b) -> (q2, y, L), (q1, y) -> (q1, y, R),
+                (q2, a) -> (q2, a, L), (q2, b) -> (q1, b, R), (q2, x) -> (q0, x, R),
+                (q2, y) -> (q2, y, L),
+                (q3, B) -> (q4, B, L), (q3, y) -> (q3, y, R),
+                (q5, a) -> (q0, a, R)
+            }
+            Start = q0
+            FinalSet = {q4}
+            Empty = B",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn dot_of_tm_bar() {
+        assert_eq!(
+            tm_bar().to_dot(),
+            r#"digraph tm {
+    rankdir=LR;
+    node [shape=circle];
+    __start [shape=point];
+    __start -> "q0";
+    "q4" [shape=doublecircle];
+    "q0" -> "q1" [label="a→x,R"];
+    "q0" -> "q3" [label="y→y,R"];
+    "q1" -> "q1" [label="a→a,R | y→y,R"];
+    "q1" -> "q2" [label="b→y,L"];
+    "q2" -> "q0" [label="x→x,R"];
+    "q2" -> "q2" [label="a→a,L | y→y,L"];
+    "q3" -> "q3" [label="y→y,R"];
+    "q3" -> "q4" [label="B→B,L"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn mermaid_of_tm_bar() {
+        assert_eq!(
+            tm_bar().to_mermaid(),
+            "stateDiagram-v2
+    direction LR
+    classDef reject stroke:red
+    state \"q0\" as s0
+    state \"q1\" as s1
+    state \"q3\" as s2
+    state \"q2\" as s3
+    state \"q4\" as s4
+    [*] --> s0
+    s4 --> [*]
+    s0 --> s1 : a→x,R
+    s0 --> s2 : y→y,R
+    s1 --> s1 : a→a,R | y→y,R
+    s1 --> s3 : b→y,L
+    s3 --> s0 : x→x,R
+    s3 --> s3 : a→a,L | y→y,L
+    s2 --> s2 : y→y,R
+    s2 --> s4 : B→B,L
+"
+        );
+        let mermaid = tm_bar_extended().to_mermaid();
+        assert!(mermaid.contains("    state \"q5\" as s5\n    state \"q6\" as s6\n"));
+        assert!(mermaid.contains("    class s6 reject\n"));
+        assert!(mermaid.contains("    s3 --> s1 : b→b,R\n"));
+    }
+
+    #[test]
+    fn tikz_of_tm_bar() {
+        assert_eq!(
+            tm_bar().to_tikz(),
+            r"% \usetikzlibrary{automata, arrows}
+\begin{tikzpicture}[->, >=stealth, auto, semithick]
+    \node[state, initial] (s0) at (0.0, 0.0) {q0};
+    \node[state] (s1) at (3.0, 0.0) {q1};
+    \node[state] (s2) at (3.0, -2.0) {q3};
+    \node[state] (s3) at (6.0, 0.0) {q2};
+    \node[state, accepting] (s4) at (6.0, -2.0) {q4};
+    \path
+        (s0) edge node {a$\to$x,R} (s1)
+        (s0) edge node {y$\to$y,R} (s2)
+        (s1) edge [loop above] node {a$\to$a,R $|$ y$\to$y,R} (s1)
+        (s1) edge node {b$\to$y,L} (s3)
+        (s3) edge node {x$\to$x,R} (s0)
+        (s3) edge [loop above] node {a$\to$a,L $|$ y$\to$y,L} (s3)
+        (s2) edge [loop above] node {y$\to$y,R} (s2)
+        (s2) edge node {B$\to$B,L} (s4)
+    ;
+\end{tikzpicture}
+"
+        );
+    }
+
+    #[test]
+    fn tikz_bends_pairs_and_puts_unreachable_states_last() {
+        assert_eq!(
+            tm_bar_extended().to_tikz(),
+            r"% \usetikzlibrary{automata, arrows}
+\begin{tikzpicture}[->, >=stealth, auto, semithick]
+    \node[state, initial] (s0) at (0.0, 0.0) {q0};
+    \node[state] (s1) at (3.0, 0.0) {q1};
+    \node[state] (s2) at (3.0, -2.0) {q3};
+    \node[state] (s3) at (6.0, 0.0) {q2};
+    \node[state, accepting] (s4) at (6.0, -2.0) {q4};
+    \node[state] (s5) at (9.0, 0.0) {q5};
+    \node[state, draw=red] (s6) at (9.0, -2.0) {q6};
+    \path
+        (s0) edge node {a$\to$x,R} (s1)
+        (s0) edge node {y$\to$y,R} (s2)
+        (s1) edge [loop above] node {a$\to$a,R $|$ y$\to$y,R} (s1)
+        (s1) edge [bend left] node {b$\to$y,L} (s3)
+        (s3) edge node {x$\to$x,R} (s0)
+        (s3) edge [bend left] node {b$\to$b,R} (s1)
+        (s3) edge [loop above] node {a$\to$a,L $|$ y$\to$y,L} (s3)
+        (s2) edge [loop above] node {y$\to$y,R} (s2)
+        (s2) edge node {B$\to$B,L} (s4)
+        (s5) edge node {a$\to$a,R} (s0)
+    ;
+\end{tikzpicture}
+"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Busy beaver standard text format, e.g. `1RB1LB_1LA1RZ`, and the binary
+//! seed database of bbchallenge.
+//!
+//! States are `A`, `B`, ... with one `_` separated group each, a group holds
+//! a `write, move, next` triple for every symbol `0`, `1`, ... where `0` is
+//! the empty symbol. `---` is a missing transition, so the machine rejects
+//! there. A next state without a group, usually `Z`, is an accept state.
+use crate::tm::{HeadDirection, Symbol, TMBuilder, TransferFnItem, TM};
+
+use rustc_hash::FxHashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Size of the seed database header.
+pub const SEED_DB_HEADER_LEN: u64 = 30;
+
+/// Size of a machine record of the seed database, 5 states and 2 symbols.
+pub const SEED_DB_RECORD_LEN: u64 = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("syntax: {0}")]
+    Syntax(String),
+    #[error("semanic: {0}")]
+    Semantic(String),
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub fn parse<T: AsRef<str>>(content: T) -> Result<TM, Error> {
+    let groups = content
+        .as_ref()
+        .trim()
+        .split('_')
+        .map(|g| g.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let n_syms = groups[0].len() / 3;
+    if n_syms == 0 || n_syms > 10 {
+        return Err(Error::Syntax(format!(
+            "invalid group: {}",
+            content.as_ref()
+        )));
+    }
+    if groups.len() > 26 {
+        return Err(Error::Syntax("more than 26 states".to_string()));
+    }
+    let states = (0..groups.len()).map(state_name).collect::<Vec<_>>();
+
+    let mut builder = TMBuilder::new()
+        .start_state("A")
+        .states(states.iter().cloned())
+        .syms((1..n_syms).map(sym_name))
+        .empty_sym('0');
+    let mut fns = Vec::new();
+    for (i, group) in groups.iter().enumerate() {
+        if group.len() != n_syms * 3 {
+            return Err(Error::Syntax(format!(
+                "group {} has {} symbols, expected {}",
+                states[i],
+                group.len() / 3,
+                n_syms
+            )));
+        }
+        for (sym, t) in group.chunks(3).enumerate() {
+            let (write, dir, next) = match t {
+                ['-', '-', '-'] => continue,
+                [w, d, n] => (*w, *d, *n),
+                _ => unreachable!(),
+            };
+            let write = write
+                .to_digit(10)
+                .filter(|w| (*w as usize) < n_syms)
+                .ok_or_else(|| Error::Syntax(format!("invalid symbol: {}", write)))?;
+            let dir = match dir {
+                'L' => HeadDirection::Left,
+                'R' => HeadDirection::Right,
+                _ => return Err(Error::Syntax(format!("invalid head direction: {}", dir))),
+            };
+            if !next.is_ascii_uppercase() {
+                return Err(Error::Syntax(format!("invalid state: {}", next)));
+            }
+            let next = next.to_string();
+            if !states.contains(&next) {
+                builder = builder.accept_state(next.as_str());
+            }
+            fns.push(
+                TransferFnItem::new()
+                    .from(states[i].as_str(), sym_name(sym))
+                    .to(next, Some(sym_name(write as usize)), dir),
+            );
+        }
+    }
+    builder.transfer_fns(fns).build().map_err(Error::Semantic)
+}
+
+/// Write `tm` in the standard text format.
+///
+/// The start state becomes `A`, the other states follow in name order and
+/// accept states become `Z`. The empty symbol becomes `0`, the others follow
+/// in order. Fails on machines the format can not express.
+pub fn to_bb(tm: &TM) -> Result<String, Error> {
+    let mut states = tm
+        .states()
+        .filter(|s| !tm.accept(s) && *s != tm.start_state())
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    states.sort();
+    states.insert(0, tm.start_state().to_string());
+    if states.len() > 25 {
+        return Err(Error::Semantic("more than 25 states".to_string()));
+    }
+    let names = states
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.as_str(), state_name(i)))
+        .collect::<FxHashMap<_, _>>();
+
+    let mut syms = tm
+        .tape_syms()
+        .chain(
+            tm.transfer_fns()
+                .flat_map(|(_, s0, _, s1, _)| Some(s0).into_iter().chain(s1)),
+        )
+        .filter(|s| *s != tm.empty_sym())
+        .collect::<Vec<_>>();
+    syms.sort_unstable();
+    syms.dedup();
+    syms.insert(0, tm.empty_sym());
+    if syms.len() > 10 {
+        return Err(Error::Semantic("more than 10 symbols".to_string()));
+    }
+    let sym_ids = syms
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (*s, sym_name(i)))
+        .collect::<FxHashMap<_, _>>();
+
+    let mut groups = Vec::new();
+    for s in states.iter() {
+        let mut group = String::new();
+        for sym in syms.iter().copied() {
+            match tm.transfer(s[..].into(), sym) {
+                None => group += "---",
+                Some((next, write, dir)) => {
+                    group.push(sym_ids[&write.unwrap_or(sym)]);
+                    group.push(match dir {
+                        HeadDirection::Stop => {
+                            return Err(Error::Semantic(format!(
+                                "head of ({}, {}) stays in place",
+                                s, sym
+                            )))
+                        }
+                        dir => dir.letter(),
+                    });
+                    if tm.accept(&next) {
+                        group.push('Z');
+                    } else {
+                        group += &names[&next[..]];
+                    }
+                }
+            }
+        }
+        groups.push(group);
+    }
+    Ok(groups.join("_"))
+}
+
+/// Seed database of bbchallenge, 5 states 2 symbols machines.
+///
+/// After a 30 bytes header, each machine takes 30 bytes: for every state and
+/// symbol, the symbol to write, the move (0 right, 1 left) and the next
+/// state (1 for `A`, ...), all zeros for a missing transition.
+pub struct SeedDb {
+    file: File,
+    len: u64,
+}
+
+impl SeedDb {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        if size < SEED_DB_HEADER_LEN
+            || !(size - SEED_DB_HEADER_LEN).is_multiple_of(SEED_DB_RECORD_LEN)
+        {
+            return Err(Error::Syntax(format!(
+                "invalid seed database size: {}",
+                size
+            )));
+        }
+        Ok(Self {
+            file,
+            len: (size - SEED_DB_HEADER_LEN) / SEED_DB_RECORD_LEN,
+        })
+    }
+
+    /// Number of machines.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The `i`-th machine in the standard text format.
+    pub fn get_str(&mut self, i: u64) -> Result<String, Error> {
+        if i >= self.len {
+            return Err(Error::Semantic(format!("no machine {}", i)));
+        }
+        let mut record = [0; SEED_DB_RECORD_LEN as usize];
+        self.file
+            .seek(SeekFrom::Start(SEED_DB_HEADER_LEN + i * SEED_DB_RECORD_LEN))?;
+        self.file.read_exact(&mut record)?;
+        decode_record(&record)
+    }
+
+    /// The `i`-th machine.
+    pub fn get(&mut self, i: u64) -> Result<TM, Error> {
+        parse(self.get_str(i)?)
+    }
+}
+
+fn decode_record(record: &[u8]) -> Result<String, Error> {
+    let groups = record
+        .chunks(6)
+        .map(|group| {
+            let mut s = String::new();
+            for t in group.chunks(3) {
+                match t {
+                    [_, _, 0] => s += "---",
+                    [w @ 0..=1, d @ 0..=1, n @ 1..=5] => {
+                        s.push(sym_name(*w as usize));
+                        s.push(if *d == 0 { 'R' } else { 'L' });
+                        s += &state_name(*n as usize - 1);
+                    }
+                    _ => return Err(Error::Syntax(format!("invalid record: {:?}", record))),
+                }
+            }
+            Ok(s)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(groups.join("_"))
+}
+
+fn state_name(i: usize) -> String {
+    ((b'A' + i as u8) as char).to_string()
+}
+
+fn sym_name(i: usize) -> Symbol {
+    std::char::from_digit(i as u32, 10).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// The 5 states champion with its halting transition left out.
+    const CHAMPION: &str = "1RB1LC_1RC1RB_1RD0LE_1LA1LD_---0LA";
+
+    #[rustfmt::skip]
+    const CHAMPION_RECORD: [u8; 30] = [
+        1, 0, 2, 1, 1, 3,
+        1, 0, 3, 1, 0, 2,
+        1, 0, 4, 0, 1, 5,
+        1, 1, 1, 1, 1, 4,
+        0, 0, 0, 0, 1, 1,
+    ];
+
+    #[test]
+    fn round_trips() {
+        for machine in ["1RB1LB_1LA1RZ", "1RB---_1LA1RZ", CHAMPION] {
+            assert_eq!(to_bb(&parse(machine).unwrap()).unwrap(), machine);
+        }
+        let tm = parse("1RB---_1LA1RZ").unwrap();
+        assert!(tm.transfer("A".into(), '1').is_none());
+        assert!(tm.accept("Z"));
+    }
+
+    #[test]
+    fn rejects_invalid_machines() {
+        let e = parse("1RB1LB_1LA").err().unwrap();
+        assert!(e.to_string().contains("group B has 1 symbols"), "{}", e);
+        assert!(parse("1RB1LB_1SA1RZ").is_err());
+        assert!(parse("1RB1LB_2LA1RZ").is_err());
+
+        let stays = TMBuilder::new()
+            .start_state("q0")
+            .accept_state("q1")
+            .sym('1')
+            .empty_sym('0')
+            .transfer_fns(vec![TransferFnItem::new().from("q0", '0').to(
+                "q1",
+                Some('1'),
+                HeadDirection::Stop,
+            )])
+            .build()
+            .unwrap();
+        let e = to_bb(&stays).err().unwrap();
+        assert!(e.to_string().contains("stays in place"), "{}", e);
+    }
+
+    #[test]
+    fn decodes_records() {
+        assert_eq!(decode_record(&CHAMPION_RECORD).unwrap(), CHAMPION);
+        let mut invalid = CHAMPION_RECORD;
+        invalid[1] = 2;
+        assert!(decode_record(&invalid).is_err());
+    }
+
+    #[test]
+    fn reads_seed_database() {
+        let path = std::env::temp_dir().join(format!("seed-db-{}", std::process::id()));
+        let mut content = vec![0xff; SEED_DB_HEADER_LEN as usize];
+        content.extend_from_slice(&[0; SEED_DB_RECORD_LEN as usize]);
+        content.extend_from_slice(&CHAMPION_RECORD);
+        fs::write(&path, &content).unwrap();
+        let mut db = SeedDb::open(&path).unwrap();
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.get_str(0).unwrap(), "------_------_------_------_------");
+        assert_eq!(db.get_str(1).unwrap(), CHAMPION);
+        assert_eq!(to_bb(&db.get(1).unwrap()).unwrap(), CHAMPION);
+        assert!(db.get_str(2).is_err());
+
+        fs::write(&path, &content[..content.len() - 1]).unwrap();
+        let truncated = SeedDb::open(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(truncated.is_err());
+    }
+}
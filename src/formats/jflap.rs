@@ -0,0 +1,468 @@
+//! JFLAP `.jff` files of single-tape turing machines.
+//!
+//! An empty `<read/>` or `<write/>` stands for the empty symbol, `~` reads
+//! any symbol without an explicit transition or writes back the symbol
+//! read. As a read it covers the symbols of the transitions and the ones
+//! given to [`parse_with_syms`]. `S` keeps the head in place. Two `~`
+//! transitions from one state are nondeterministic, like two explicit ones on
+//! the same symbol. Building blocks are not supported. State coordinates
+//! are not part of a [`TM`] and are carried in a [`Layout`].
+use crate::tm::{HeadDirection, Symbol, TMBuilder, TransferFnItem, TM};
+
+use pest::{iterators::Pair, Parser};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt::Write;
+
+/// Position of each state in the JFLAP editor, by state name.
+pub type Layout = FxHashMap<String, (f64, f64)>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("parse: {0}")]
+    Parse(Box<pest::error::Error<Rule>>),
+    #[error("semanic: {0}")]
+    Semantic(String),
+}
+
+impl From<pest::error::Error<Rule>> for Error {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        Error::Parse(Box::new(e))
+    }
+}
+
+/// XML element, only what `.jff` files use.
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.as_str())
+    }
+}
+
+/// Read a `.jff` file, every non-empty symbol of its transitions is taken as
+/// an input symbol.
+pub fn parse<T: AsRef<str>>(content: T) -> Result<(TM, Layout), Error> {
+    parse_with_syms(content, &[])
+}
+
+/// [`parse`] with `input_syms` as input symbols too, e.g. the symbols of the
+/// inputs to run, so that `~` reads them.
+pub fn parse_with_syms<T: AsRef<str>>(
+    content: T,
+    input_syms: &[Symbol],
+) -> Result<(TM, Layout), Error> {
+    let root = JffParser::parse(Rule::ROOT, content.as_ref())?
+        .next()
+        .map(parse_element)
+        .unwrap();
+    if root.name != "structure" {
+        return Err(semantic("root element is not <structure>"));
+    }
+    match root.child_text("type").map(str::trim) {
+        Some("turing") => (),
+        t => return Err(semantic(format!("not a turing machine: {:?}", t))),
+    }
+    if let Some(tapes) = root.child_text("tapes") {
+        if tapes.trim() != "1" {
+            return Err(semantic("only single-tape machines are supported"));
+        }
+    }
+    let automaton = root
+        .child("automaton")
+        .ok_or_else(|| semantic("no <automaton>"))?;
+
+    let mut builder = TMBuilder::new();
+    let mut names = FxHashMap::default();
+    let mut layout = Layout::default();
+    for s in automaton.children.iter() {
+        if s.name == "block" {
+            return Err(semantic("building blocks are not supported"));
+        }
+        if s.name != "state" {
+            continue;
+        }
+        let id = s.attr("id").ok_or_else(|| semantic("state without id"))?;
+        let name = s
+            .attr("name")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("q{}", id));
+        if let (Some(x), Some(y)) = (s.child_text("x"), s.child_text("y")) {
+            layout.insert(name.clone(), (parse_coord(x)?, parse_coord(y)?));
+        }
+        builder = builder.state(name.as_str());
+        if s.child("initial").is_some() {
+            builder = builder.start_state(name.as_str());
+        }
+        if s.child("final").is_some() {
+            builder = builder.accept_state(name.as_str());
+        }
+        names.insert(id.to_string(), name);
+    }
+
+    // (from, read, to, write, dir), `None` for `~`.
+    let mut items = Vec::new();
+    let mut tape_syms = FxHashSet::default();
+    for t in automaton.children.iter().filter(|c| c.name == "transition") {
+        let state = |tag: &str| -> Result<String, Error> {
+            let id = t
+                .child_text(tag)
+                .ok_or_else(|| semantic(format!("transition without <{}>", tag)))?;
+            names
+                .get(id.trim())
+                .cloned()
+                .ok_or_else(|| semantic(format!("transition refers to unknown state {}", id)))
+        };
+        let from = state("from")?;
+        let to = state("to")?;
+        let read = parse_sym(t.child_text("read"))?;
+        let write = parse_sym(t.child_text("write"))?;
+        let dir = parse_dir(t.child_text("move").unwrap_or("S"))?;
+        tape_syms.extend(read);
+        tape_syms.extend(write);
+        items.push((from, read, to, write, dir));
+    }
+    tape_syms.extend(input_syms.iter().map(|s| Some(*s)));
+
+    // blank is represented by `None` inside `Some`, pick a symbol for it.
+    let empty = ['B', '_', '#', '□']
+        .iter()
+        .copied()
+        .find(|s| !tape_syms.contains(&Some(*s)))
+        .ok_or_else(|| semantic("no free symbol left for the empty symbol"))?;
+    let syms = tape_syms
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<FxHashSet<_>>();
+    let all = syms.iter().copied().chain(Some(empty)).collect::<Vec<_>>();
+
+    let mut explicit = FxHashSet::default();
+    let mut wildcard = FxHashSet::default();
+    for (from, read, _, _, _) in items.iter() {
+        let fresh = match read {
+            Some(read) => explicit.insert((from.clone(), read.unwrap_or(empty))),
+            None => wildcard.insert(from.clone()),
+        };
+        if !fresh {
+            let read = read.map_or('~', |r| r.unwrap_or(empty));
+            return Err(semantic(format!(
+                "nondeterministic transitions from ({}, {})",
+                from, read
+            )));
+        }
+    }
+    let mut fns = Vec::new();
+    for (from, read, to, write, dir) in items {
+        let write = write.map(|w| w.unwrap_or(empty));
+        let reads = match read {
+            Some(read) => vec![read.unwrap_or(empty)],
+            None => all
+                .iter()
+                .copied()
+                .filter(|s| !explicit.contains(&(from.clone(), *s)))
+                .collect(),
+        };
+        for read in reads {
+            fns.push(
+                TransferFnItem::new()
+                    .from(from.as_str(), read)
+                    .to(to.as_str(), write, dir),
+            );
+        }
+    }
+
+    let tm = builder
+        .syms(syms)
+        .empty_sym(empty)
+        .transfer_fns(fns)
+        .build()
+        .map_err(Error::Semantic)?;
+    Ok((tm, layout))
+}
+
+/// Write `tm` as a `.jff` file, states missing in `layout` are put on a row.
+pub fn to_jff(tm: &TM, layout: &Layout) -> String {
+    let mut states = tm.states().map(|s| s.to_string()).collect::<Vec<_>>();
+    states.sort();
+    let start = states
+        .iter()
+        .position(|s| s[..] == tm.start_state()[..])
+        .unwrap();
+    let start = states.remove(start);
+    states.insert(0, start);
+    let ids = states
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.as_str(), i))
+        .collect::<FxHashMap<_, _>>();
+
+    let mut out = String::new();
+    out += "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n";
+    out += "<structure>\n\t<type>turing</type>\n\t<automaton>\n";
+    for (i, s) in states.iter().enumerate() {
+        let (x, y) = layout
+            .get(s)
+            .copied()
+            .unwrap_or((100.0 + 150.0 * i as f64, 100.0));
+        writeln!(out, "\t\t<state id=\"{}\" name=\"{}\">", i, escape(s)).unwrap();
+        writeln!(out, "\t\t\t<x>{:.1}</x>", x).unwrap();
+        writeln!(out, "\t\t\t<y>{:.1}</y>", y).unwrap();
+        if i == 0 {
+            out += "\t\t\t<initial/>\n";
+        }
+        if tm.accept(s) {
+            out += "\t\t\t<final/>\n";
+        }
+        out += "\t\t</state>\n";
+    }
+
+    let mut fns = tm
+        .transfer_fns()
+        .map(|(s0, sym0, s1, sym1, dir)| (ids[&s0[..]], sym0, ids[&s1[..]], sym1, dir))
+        .collect::<Vec<_>>();
+    fns.sort_by_key(|(s0, sym0, _, _, _)| (*s0, *sym0));
+    for (s0, sym0, s1, sym1, dir) in fns {
+        let sym = |s: Symbol| {
+            if s == tm.empty_sym() {
+                String::new()
+            } else {
+                escape(&s.to_string())
+            }
+        };
+        out += "\t\t<transition>\n";
+        writeln!(out, "\t\t\t<from>{}</from>", s0).unwrap();
+        writeln!(out, "\t\t\t<to>{}</to>", s1).unwrap();
+        write_elem(&mut out, "read", &sym(sym0));
+        write_elem(&mut out, "write", &sym(sym1.unwrap_or(sym0)));
+        writeln!(out, "\t\t\t<move>{}</move>", dir).unwrap();
+        out += "\t\t</transition>\n";
+    }
+    out += "\t</automaton>\n</structure>\n";
+    out
+}
+
+fn write_elem(out: &mut String, name: &str, text: &str) {
+    if text.is_empty() {
+        writeln!(out, "\t\t\t<{}/>", name).unwrap();
+    } else {
+        writeln!(out, "\t\t\t<{}>{}</{}>", name, text, name).unwrap();
+    }
+}
+
+fn parse_element(p: Pair<Rule>) -> Element {
+    let mut inner = p.into_inner();
+    let name = inner.next().unwrap().as_str().to_string();
+    let mut elem = Element {
+        name,
+        attrs: Vec::new(),
+        children: Vec::new(),
+        text: String::new(),
+    };
+    for p in inner {
+        match p.as_rule() {
+            Rule::Attr => {
+                let mut p = p.into_inner();
+                let name = p.next().unwrap().as_str().to_string();
+                let value = unescape(p.next().unwrap().as_str());
+                elem.attrs.push((name, value));
+            }
+            Rule::Element => elem.children.push(parse_element(p)),
+            Rule::Text => elem.text += &unescape(p.as_str()),
+            _ => (),
+        }
+    }
+    elem
+}
+
+/// `None` for `~`, `Some(None)` for the empty symbol.
+fn parse_sym(text: Option<&str>) -> Result<Option<Option<Symbol>>, Error> {
+    let text = text.unwrap_or("");
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => Ok(Some(None)),
+        (Some('~'), None) => Ok(None),
+        (Some(s), None) => Ok(Some(Some(s))),
+        _ => Err(semantic(format!("invalid symbol: {}", text))),
+    }
+}
+
+fn parse_dir(text: &str) -> Result<HeadDirection, Error> {
+    match text.trim() {
+        "L" => Ok(HeadDirection::Left),
+        "R" => Ok(HeadDirection::Right),
+        "S" => Ok(HeadDirection::Stop),
+        dir => Err(semantic(format!("invalid head direction: {}", dir))),
+    }
+}
+
+fn parse_coord(text: &str) -> Result<f64, Error> {
+    text.trim()
+        .parse()
+        .map_err(|_| semantic(format!("invalid coordinate: {}", text)))
+}
+
+fn semantic<T: Into<String>>(msg: T) -> Error {
+    Error::Semantic(msg.into())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Decode the predefined entities and numeric character references, other
+/// entities are kept as they are.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out += &rest[..i];
+        rest = &rest[i..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "amp" => Some('&'),
+                e if e.starts_with("#x") => u32::from_str_radix(&e[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                e if e.starts_with('#') => e[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out + rest
+}
+
+#[derive(Parser)]
+#[grammar = "formats/jflap.pest"]
+struct JffParser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify::Words, Runner};
+
+    const A_N_B_N: &str = include_str!("../../examples/a_n_b_n.jff");
+
+    fn verdicts(tm: &TM) -> Vec<crate::Verdict> {
+        let mut runner = Runner::with_tm(tm);
+        Words::new(vec!['a', 'b'], 6)
+            .map(|input| {
+                runner.feed_str(input);
+                runner.run(1_000).verdict
+            })
+            .collect()
+    }
+
+    fn machine(transitions: &str) -> String {
+        format!(
+            "<structure><type>turing</type><automaton>\
+             <state id=\"0\" name=\"q0\"><initial/></state>\
+             <state id=\"1\" name=\"q1\"><final/></state>\
+             {}</automaton></structure>",
+            transitions
+        )
+    }
+
+    #[test]
+    fn sample_round_trips() {
+        let (tm, layout) = parse(A_N_B_N).unwrap();
+        assert_eq!(layout["q4"], (700.0, 100.0));
+        assert_eq!(to_jff(&tm, &layout), A_N_B_N);
+
+        let expected = crate::test_util::a_n_b_n();
+        assert_eq!(verdicts(&tm), verdicts(&expected));
+    }
+
+    #[test]
+    fn wildcards_fill_in_missing_reads() {
+        let (tm, _) = parse(machine(
+            "<transition><from>0</from><to>0</to><read>a</read><write>b</write><move>R</move></transition>\
+             <transition><from>0</from><to>1</to><read>~</read><write>~</write><move>S</move></transition>",
+        ))
+        .unwrap();
+        let next = |sym| tm.transfer("q0".into(), sym).unwrap().0.to_string();
+        assert_eq!(next('a'), "q0");
+        assert_eq!(next('b'), "q1");
+        assert_eq!(next(tm.empty_sym()), "q1");
+    }
+
+    #[test]
+    fn wildcards_read_input_symbols() {
+        let content = machine(
+            "<transition><from>0</from><to>0</to><read>~</read><write>~</write><move>R</move></transition>\
+             <transition><from>0</from><to>1</to><read/><write/><move>S</move></transition>",
+        );
+        let (tm, _) = parse_with_syms(&content, &['a', 'b']).unwrap();
+        let mut runner = Runner::with_tm(&tm);
+        runner.feed_str("ab");
+        assert_eq!(runner.run(100).verdict, crate::Verdict::Accept);
+        assert_eq!(runner.steps(), 3);
+        assert_eq!(parse(&content).unwrap().0.syms().count(), 0);
+    }
+
+    #[test]
+    fn rejects_blocks() {
+        let e = parse(machine(
+            "<block id=\"2\" name=\"q2\"><tag>sub</tag></block>",
+        ))
+        .err()
+        .unwrap();
+        assert!(e.to_string().contains("building blocks"), "{}", e);
+    }
+
+    #[test]
+    fn rejects_overlapping_wildcards() {
+        let wildcard =
+            "<transition><from>0</from><to>1</to><read>~</read><write/><move>R</move></transition>";
+        let e = parse(machine(&wildcard.repeat(2))).err().unwrap();
+        assert!(e.to_string().contains("nondeterministic"), "{}", e);
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(
+            unescape("&lt;&#65;&#x42;&amp;lt;&unknown;&"),
+            "<AB&lt;&unknown;&"
+        );
+        let (tm, _) = parse(machine(
+            "<transition><from>0</from><to>1</to><read>&#38;</read><write>&#x3C;</write><move>R</move></transition>",
+        ))
+        .unwrap();
+        let (_, write, _) = tm.transfer("q0".into(), '&').unwrap();
+        assert_eq!(write, Some('<'));
+        assert_eq!(unescape(&escape("<a & \"b\">")), "<a & \"b\">");
+    }
+}
@@ -0,0 +1,365 @@
+//! Line format of the online simulators, one `state sym newsym dir newstate`
+//! rule per line.
+//!
+//! `_` is the empty symbol and `;` starts a comment. `*` matches any state
+//! or symbol on the left and keeps the state, symbol or head position on the
+//! right. As a symbol on the left it covers the symbols of the rules and the
+//! ones given to [`parse_with_syms`]. An exact rule wins over `state *`,
+//! which wins over `* sym` and then `* *`. The machine starts in `0` and
+//! stops on entering a state named `halt...`, `halt-reject` rejects while the
+//! other halting states accept.
+use crate::tm::{HeadDirection, Symbol, TMBuilder, TransferFnItem, TM};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt::Write;
+
+/// Empty symbol of the format.
+pub const EMPTY_SYM: Symbol = '_';
+
+/// Start state of the format, a machine without rules for it rejects at
+/// once.
+pub const START: &str = "0";
+
+/// The halting state that rejects.
+const REJECT: &str = "halt-reject";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("line {0}: {1}")]
+    Syntax(usize, String),
+    #[error("semanic: {0}")]
+    Semantic(String),
+}
+
+/// Rule of the file, `None` for `*`.
+struct Rule {
+    state: Option<String>,
+    sym: Option<Symbol>,
+    next_sym: Option<Symbol>,
+    dir: HeadDirection,
+    next_state: Option<String>,
+}
+
+pub fn parse<T: AsRef<str>>(content: T) -> Result<TM, Error> {
+    parse_with_syms(content, &[])
+}
+
+/// [`parse`] with `input_syms` as input symbols too, e.g. the symbols of the
+/// inputs to run. The simulators match `*` against any symbol read, here it
+/// only covers the symbols of the rules and `input_syms`.
+pub fn parse_with_syms<T: AsRef<str>>(content: T, input_syms: &[Symbol]) -> Result<TM, Error> {
+    let mut rules = Vec::new();
+    for (i, line) in content.as_ref().lines().enumerate() {
+        let line = line.split(';').next().unwrap();
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let fields = match fields.as_slice() {
+            [] => continue,
+            [f @ .., "!"] => f,
+            f => f,
+        };
+        if fields.len() != 5 {
+            return Err(Error::Syntax(
+                i + 1,
+                format!("expected 5 fields, found {}", fields.len()),
+            ));
+        }
+        let sym = |f: &str| parse_sym(f).map_err(|e| Error::Syntax(i + 1, e));
+        let state = |f: &str| Some(f.to_string()).filter(|s| s != "*");
+        rules.push(Rule {
+            state: state(fields[0]),
+            sym: sym(fields[1])?,
+            next_sym: sym(fields[2])?,
+            dir: parse_dir(fields[3]).map_err(|e| Error::Syntax(i + 1, e))?,
+            next_state: state(fields[4]),
+        });
+    }
+    let mut states = vec![START];
+    let mut seen = FxHashSet::default();
+    seen.insert(START);
+    for s in rules
+        .iter()
+        .flat_map(|r| r.state.iter().chain(&r.next_state))
+    {
+        if seen.insert(s.as_str()) {
+            states.push(s.as_str());
+        }
+    }
+    let mut syms = rules
+        .iter()
+        .flat_map(|r| r.sym.iter().chain(&r.next_sym))
+        .chain(input_syms)
+        .copied()
+        .filter(|s| *s != EMPTY_SYM)
+        .collect::<Vec<_>>();
+    syms.sort_unstable();
+    syms.dedup();
+    let tape_syms = syms
+        .iter()
+        .copied()
+        .chain(Some(EMPTY_SYM))
+        .collect::<Vec<_>>();
+
+    let mut exact = FxHashMap::default();
+    for r in rules.iter() {
+        if exact.insert((&r.state, r.sym), r).is_some() {
+            return Err(Error::Semantic(format!(
+                "duplicated rule for {} {}",
+                r.state.as_deref().unwrap_or("*"),
+                r.sym.unwrap_or('*')
+            )));
+        }
+    }
+
+    let mut fns = Vec::new();
+    for state in states.iter().filter(|s| !is_halt(s)) {
+        let s = Some(state.to_string());
+        for sym in tape_syms.iter().copied() {
+            let rule = [
+                (&s, Some(sym)),
+                (&s, None),
+                (&None, Some(sym)),
+                (&None, None),
+            ]
+            .iter()
+            .find_map(|k| exact.get(k));
+            if let Some(r) = rule {
+                let next_state = r.next_state.as_deref().unwrap_or(state);
+                fns.push(
+                    TransferFnItem::new()
+                        .from(*state, sym)
+                        .to(next_state, r.next_sym, r.dir),
+                );
+            }
+        }
+    }
+
+    TMBuilder::new()
+        .states(states.iter().copied())
+        .start_state(START)
+        .accept_states(
+            states
+                .iter()
+                .copied()
+                .filter(|s| is_halt(s) && *s != REJECT),
+        )
+        .syms(syms)
+        .empty_sym(EMPTY_SYM)
+        .transfer_fns(fns)
+        .build()
+        .map_err(Error::Semantic)
+}
+
+/// Write `tm` as rules, renaming states so that the machine starts in `0`
+/// and halts in `halt...` states. The empty symbol is written as `_`.
+///
+/// An accepting start state only accepts once entered again, so transitions
+/// into it lead to a separate `halt-` state.
+pub fn to_lines(tm: &TM) -> String {
+    let start = tm.start_state();
+    let mut names = FxHashMap::default();
+    let mut taken = tm.states().map(|s| s.to_string()).collect::<FxHashSet<_>>();
+    taken.insert(START.to_string());
+    let mut rename = |from: &str, to: String| {
+        let mut to = to;
+        while taken.contains(&to) && to != from {
+            to.push('_');
+        }
+        taken.insert(to.clone());
+        to
+    };
+    let mut states = tm.states().map(|s| s.to_string()).collect::<Vec<_>>();
+    states.sort();
+    let has_rules = |s: &str| tm.transfer_fns().any(|f| &f.0[..] == s);
+    for s in states.iter() {
+        let name = if tm.accept(s) && (!is_halt(s) || s == REJECT) {
+            rename(s, format!("halt-{}", s))
+        } else if !tm.accept(s) && is_halt(s) && (s != REJECT || has_rules(s)) {
+            rename(s, format!("q{}", s))
+        } else if s == START && s[..] != start[..] {
+            rename(s, format!("{}_", s))
+        } else {
+            continue;
+        };
+        names.insert(s.to_string(), name);
+    }
+    let name = |s: &str| names.get(s).cloned().unwrap_or_else(|| s.to_string());
+    let from = |s: &str| {
+        if s == &start[..] {
+            START.to_string()
+        } else {
+            name(s)
+        }
+    };
+    let to = |s: &str| {
+        if s == &start[..] && !tm.accept(s) {
+            START.to_string()
+        } else {
+            name(s)
+        }
+    };
+    let sym = |s: Symbol| if s == tm.empty_sym() { EMPTY_SYM } else { s };
+
+    let mut fns = tm
+        .transfer_fns()
+        .filter(|(s0, _, _, _, _)| !tm.accept(s0) || *s0 == start)
+        .map(|(s0, sym0, s1, sym1, dir)| {
+            let dir = match dir {
+                HeadDirection::Stop => '*',
+                dir => dir.letter().to_ascii_lowercase(),
+            };
+            (from(s0), sym(sym0), sym(sym1.unwrap_or(sym0)), dir, to(s1))
+        })
+        .collect::<Vec<_>>();
+    fns.sort();
+
+    let mut out = String::new();
+    for (s0, sym0, sym1, dir, s1) in fns {
+        writeln!(out, "{} {} {} {} {}", s0, sym0, sym1, dir, s1).unwrap();
+    }
+    out
+}
+
+fn is_halt(s: &str) -> bool {
+    s.starts_with("halt")
+}
+
+fn parse_sym(f: &str) -> Result<Option<Symbol>, String> {
+    let mut chars = f.chars();
+    match (chars.next(), chars.next()) {
+        (Some('*'), None) => Ok(None),
+        (Some(s), None) => Ok(Some(s)),
+        _ => Err(format!("invalid symbol: {}", f)),
+    }
+}
+
+fn parse_dir(f: &str) -> Result<HeadDirection, String> {
+    match f {
+        "l" | "L" => Ok(HeadDirection::Left),
+        "r" | "R" => Ok(HeadDirection::Right),
+        "*" | "s" | "S" => Ok(HeadDirection::Stop),
+        _ => Err(format!("invalid head direction: {}", f)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify::Words, RunOutcome, Runner, Verdict};
+
+    fn outcomes(tm: &TM) -> Vec<RunOutcome> {
+        let mut runner = Runner::with_tm(tm);
+        Words::new(vec!['a', 'b'], 4)
+            .map(|input| {
+                runner.feed_str(input);
+                runner.run(200)
+            })
+            .collect()
+    }
+
+    fn assert_round_trips(tm: &TM) {
+        let lines = to_lines(tm);
+        let parsed = parse(&lines).unwrap();
+        assert_eq!(outcomes(&parsed), outcomes(tm), "{}", lines);
+    }
+
+    fn machine(start: &str, accept: &[&str], fns: &[(&str, Symbol, &str)]) -> TM {
+        TMBuilder::new()
+            .start_state(start)
+            .states(fns.iter().flat_map(|f| vec![f.0, f.2]))
+            .accept_states(accept.iter().copied())
+            .syms(vec!['a', 'b'])
+            .empty_sym('B')
+            .transfer_fns(fns.iter().map(|(s0, sym, s1)| {
+                TransferFnItem::new()
+                    .from(*s0, *sym)
+                    .to(*s1, None, HeadDirection::Right)
+            }))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn sample_round_trips() {
+        assert_round_trips(&crate::test_util::a_n_b_n());
+    }
+
+    #[test]
+    fn start_without_rules_stays_the_start() {
+        let tm = machine("q0", &["q2"], &[("q1", 'a', "q2")]);
+        assert_eq!(to_lines(&tm), "q1 a a r halt-q2\n");
+        assert_round_trips(&tm);
+        assert_eq!(&parse("").unwrap().start_state()[..], START);
+    }
+
+    #[test]
+    fn state_named_like_the_start_is_renamed() {
+        let tm = machine("q0", &["q1"], &[("q0", 'a', "0"), ("0", 'b', "q1")]);
+        assert_eq!(to_lines(&tm), "0 a a r 0_\n0_ b b r halt-q1\n");
+        assert_round_trips(&tm);
+    }
+
+    #[test]
+    fn accepting_start_accepts_when_entered_again() {
+        let tm = machine("q0", &["q0"], &[("q0", 'a', "q1"), ("q1", 'b', "q0")]);
+        assert_eq!(to_lines(&tm), "0 a a r q1\nq1 b b r halt-q0\n");
+        assert_round_trips(&tm);
+    }
+
+    #[test]
+    fn wildcards_cover_input_symbols() {
+        let rules = "0 * * r 0\n0 _ _ * halt\n";
+        let tm = parse_with_syms(rules, &['a', 'b']).unwrap();
+        let mut runner = Runner::with_tm(&tm);
+        runner.feed_str("ab");
+        assert_eq!(
+            runner.run(100),
+            RunOutcome {
+                verdict: Verdict::Accept,
+                steps: 3
+            }
+        );
+        assert_eq!(parse(rules).unwrap().syms().count(), 0);
+    }
+
+    #[test]
+    fn random_machines_round_trip() {
+        let names = ["q0", "0", "q2", "halt", "halt-reject"];
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % n as u64) as usize
+        };
+        for _ in 0..500 {
+            let mut fns = Vec::new();
+            for s0 in names.iter() {
+                for sym0 in ['a', 'b', 'B'] {
+                    if next(4) != 0 {
+                        let write = ['a', 'b', 'B'][next(3)];
+                        let dir = [
+                            HeadDirection::Left,
+                            HeadDirection::Right,
+                            HeadDirection::Stop,
+                        ];
+                        fns.push(TransferFnItem::new().from(*s0, sym0).to(
+                            names[next(5)],
+                            Some(write),
+                            dir[next(3)],
+                        ));
+                    }
+                }
+            }
+            let tm = TMBuilder::new()
+                .states(names.iter().copied())
+                .start_state(names[next(5)])
+                .accept_states(names.iter().copied().filter(|_| next(3) == 0))
+                .syms(vec!['a', 'b'])
+                .empty_sym('B')
+                .transfer_fns(fns)
+                .build()
+                .unwrap();
+            assert_round_trips(&tm);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Conversion between [`TM`] and the formats of other tools.
+pub mod bb;
+pub mod jflap;
+pub mod lines;
+
+use crate::parse::is_ident;
+use crate::transform::rename_states;
+use crate::{Symbol, TM};
+use rustc_hash::FxHashSet;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tm(#[from] crate::parse::Error),
+    #[error(transparent)]
+    Bb(#[from] bb::Error),
+    #[error(transparent)]
+    Jflap(#[from] jflap::Error),
+    #[error(transparent)]
+    Lines(#[from] lines::Error),
+}
+
+/// Format of a machine definition.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// The `.tm` format of [`parse`](crate::parse).
+    Tm,
+    Bb,
+    Jflap,
+    Lines,
+}
+
+impl Format {
+    /// Guess format from file extension, `.tm` if unknown.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("bb") => Format::Bb,
+            Some("jff") => Format::Jflap,
+            Some("txt") | Some("lines") => Format::Lines,
+            _ => Format::Tm,
+        }
+    }
+
+    pub fn parse<T: AsRef<str>>(self, content: T) -> Result<TM, Error> {
+        self.parse_with_syms(content, &[])
+    }
+
+    /// Parse with `input_syms` as input symbols too, so that the wildcards of
+    /// the JFLAP and line formats cover them, see [`jflap::parse_with_syms`]
+    /// and [`lines::parse_with_syms`].
+    pub fn parse_with_syms<T: AsRef<str>>(
+        self,
+        content: T,
+        input_syms: &[Symbol],
+    ) -> Result<TM, Error> {
+        Ok(match self {
+            Format::Tm => crate::parse(content)?,
+            Format::Bb => bb::parse(content)?,
+            Format::Jflap => jflap::parse_with_syms(content, input_syms)?.0,
+            Format::Lines => lines::parse_with_syms(content, input_syms)?,
+        })
+    }
+
+    /// Whether `content` has comments, [`write`](Self::write) drops them.
+    pub fn has_comments<T: AsRef<str>>(self, content: T) -> Result<bool, Error> {
+        let content = content.as_ref();
+        Ok(match self {
+            Format::Tm => !crate::parse::comments(content)?.is_empty(),
+            Format::Bb => false,
+            Format::Jflap => content.contains("<!--"),
+            Format::Lines => content.contains(';'),
+        })
+    }
+
+    /// Write `tm` in this format, JFLAP states are put on a row. States the
+    /// `.tm` format can not name, e.g. `A` or `halt-accept`, are renamed
+    /// `qA` and `qhaltaccept`.
+    pub fn write(self, tm: &TM) -> Result<String, Error> {
+        Ok(match self {
+            Format::Tm => crate::parse::to_tm(&ident_states(tm))?,
+            Format::Bb => bb::to_bb(tm)? + "\n",
+            Format::Jflap => jflap::to_jff(tm, &jflap::Layout::default()),
+            Format::Lines => lines::to_lines(tm),
+        })
+    }
+}
+
+/// Copy of `tm` with every state named by an identifier, `q` followed by the
+/// ASCII letters and digits of the name and a number if that is taken.
+fn ident_states(tm: &TM) -> TM {
+    let mut taken = tm.states().map(|s| s.to_string()).collect::<FxHashSet<_>>();
+    let mut states = tm.states().map(|s| s.to_string()).collect::<Vec<_>>();
+    states.sort();
+    let mut names = HashMap::new();
+    for s in states.into_iter().filter(|s| !is_ident(s)) {
+        let alnum = s.chars().filter(char::is_ascii_alphanumeric);
+        let base = format!("q{}", alnum.collect::<String>());
+        let name = (0..)
+            .map(|i| match i {
+                0 => base.clone(),
+                i => format!("{}{}", base, i),
+            })
+            .find(|name| is_ident(name) && !taken.contains(name))
+            .unwrap();
+        taken.insert(name.clone());
+        names.insert(s, name);
+    }
+    rename_states(tm, &names).expect("new names are not taken")
+}
+
+/// Format name, `tm`, `bb`, `jff` (or `jflap`) and `lines`.
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tm" => Ok(Format::Tm),
+            "bb" => Ok(Format::Bb),
+            "jff" | "jflap" => Ok(Format::Jflap),
+            "lines" => Ok(Format::Lines),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::runs;
+    use crate::verify::{input_alphabet, Words};
+
+    /// Convert `content` from `format` to `.tm`, checking that the machine
+    /// runs the same.
+    fn to_tm(format: Format, content: &str) -> String {
+        let tm = format.parse(content).unwrap();
+        let converted = Format::Tm.write(&tm).unwrap();
+        let inputs = Words::new(input_alphabet(&tm), 3).collect::<Vec<_>>();
+        let parsed = crate::parse(&converted).unwrap();
+        assert_eq!(runs(&parsed, &inputs), runs(&tm, &inputs), "{}", converted);
+        converted
+    }
+
+    #[test]
+    fn bb_to_tm_renames_states() {
+        let converted = to_tm(Format::Bb, "1RB1LB_1LA1RZ");
+        assert!(
+            converted.starts_with("StateSet = {qA, qB, qZ}\n"),
+            "{}",
+            converted
+        );
+    }
+
+    #[test]
+    fn lines_to_tm_renames_states() {
+        let converted = to_tm(
+            Format::Lines,
+            "0 a b r 0\n0 _ _ * halt-ok\nqhaltok b b r 0\n",
+        );
+        assert!(
+            converted.contains("(q0, _) -> (qhaltok1, _, S)"),
+            "{}",
+            converted
+        );
+    }
+}
@@ -0,0 +1,460 @@
+//! Randomised testing of machines, for alphabets and lengths too large for
+//! [`verify::exhaustive`](crate::verify::exhaustive).
+//!
+//! Inputs are drawn from a seeded generator so failures can be replayed, and
+//! a failing input is shrunk to a minimal one before being reported.
+//!
+//! ```
+//! use turing_machine::{fuzz::Fuzzer, tm_bar};
+//!
+//! let tm = tm_bar();
+//! Fuzzer::new(&tm).max_len(12).assert(|s| {
+//!     let n = s.len() / 2;
+//!     n > 0 && s == "a".repeat(n) + &"b".repeat(n)
+//! });
+//! ```
+use crate::runner::{RunOutcome, Runner, Verdict};
+use crate::tm::{Symbol, TM};
+use crate::verify::{input_alphabet, Counterexample, Mismatch};
+
+use rustc_hash::FxHashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Expansions deeper than this, or past the maximum length, pick the rule
+/// with the fewest nonterminals. Twice as deep they are dropped.
+const MAX_DEPTH: usize = 32;
+
+/// How inputs are generated.
+#[derive(Debug, Clone)]
+pub enum Strategy {
+    /// Every word up to the maximum length is equally likely, so most are
+    /// close to the maximum length.
+    Uniform,
+    /// Every length up to the maximum is equally likely, then symbols are
+    /// drawn uniformly.
+    LengthBiased,
+    /// Words derived from a grammar, e.g. to stay close to a language.
+    Grammar(Grammar),
+}
+
+/// Context free grammar over the symbols of a machine.
+///
+/// Rules are written `S -> a<S>b | ab`, one per line or separated by `;`.
+/// Nonterminals are in angle brackets, whitespace is ignored, and an empty
+/// alternative derives the empty word. The first rule defines the start.
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    start: String,
+    rules: FxHashMap<String, Vec<Vec<Item>>>,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Sym(Symbol),
+    Var(String),
+}
+
+impl FromStr for Grammar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut start = None;
+        let mut rules: FxHashMap<String, Vec<Vec<Item>>> = FxHashMap::default();
+        for rule in s.split(['\n', ';']).filter(|r| !r.trim().is_empty()) {
+            let (name, alts) = rule
+                .split_once("->")
+                .ok_or_else(|| format!("rule without ->: {}", rule.trim()))?;
+            let name = name.trim().trim_start_matches('<').trim_end_matches('>');
+            start.get_or_insert_with(|| name.to_string());
+            for alt in alts.split('|') {
+                let alt = parse_alternative(alt)?;
+                rules.entry(name.to_string()).or_default().push(alt);
+            }
+        }
+        let grammar = Grammar {
+            start: start.ok_or("empty grammar")?,
+            rules,
+        };
+        for alt in grammar.rules.values().flatten() {
+            for item in alt {
+                if let Item::Var(v) = item {
+                    if !grammar.rules.contains_key(v) {
+                        return Err(format!("no rule for <{}>", v));
+                    }
+                }
+            }
+        }
+        Ok(grammar)
+    }
+}
+
+fn parse_alternative(alt: &str) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut chars = alt.chars().filter(|c| !c.is_whitespace());
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            items.push(Item::Sym(c));
+            continue;
+        }
+        let name = chars.by_ref().take_while(|c| *c != '>').collect::<String>();
+        if name.is_empty() {
+            return Err(format!("empty nonterminal in {}", alt.trim()));
+        }
+        items.push(Item::Var(name));
+    }
+    Ok(items)
+}
+
+impl Grammar {
+    fn derive(&self, max_len: usize, rng: &mut Rng) -> String {
+        let mut out = String::new();
+        self.expand(&self.start, 0, max_len, rng, &mut out);
+        out
+    }
+
+    fn expand(&self, var: &str, depth: usize, max_len: usize, rng: &mut Rng, out: &mut String) {
+        if depth >= 2 * MAX_DEPTH {
+            return;
+        }
+        let alts = &self.rules[var];
+        let alt = if depth < MAX_DEPTH && out.chars().count() < max_len {
+            &alts[rng.below(alts.len() as u64) as usize]
+        } else {
+            alts.iter()
+                .min_by_key(|alt| alt.iter().filter(|i| matches!(i, Item::Var(_))).count())
+                .unwrap()
+        };
+        for item in alt {
+            match item {
+                Item::Sym(s) => out.push(*s),
+                Item::Var(v) => self.expand(v, depth + 1, max_len, rng, out),
+            }
+        }
+    }
+}
+
+/// Randomised tester of a machine, see the [module documentation](self).
+pub struct Fuzzer<'a> {
+    tm: &'a TM,
+    strategy: Strategy,
+    alphabet: Vec<Symbol>,
+    cases: usize,
+    max_len: usize,
+    step_limit: u64,
+    seed: u64,
+}
+
+/// A failing input, before and after shrinking.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Failure {
+    pub original: Counterexample,
+    pub shrunk: Counterexample,
+    /// Seed and index of the case that failed.
+    pub seed: u64,
+    pub case: usize,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} on {:?} after {} steps, shrunk from {:?} (case {} of seed {})",
+            self.shrunk.mismatch,
+            self.shrunk.input,
+            self.shrunk.steps,
+            self.original.input,
+            self.case,
+            self.seed
+        )
+    }
+}
+
+impl<'a> Fuzzer<'a> {
+    /// 1000 length biased cases up to 16 symbols over the input alphabet of
+    /// `tm`, 10000 steps each.
+    pub fn new(tm: &'a TM) -> Self {
+        Self {
+            tm,
+            strategy: Strategy::LengthBiased,
+            alphabet: input_alphabet(tm),
+            cases: 1000,
+            max_len: 16,
+            step_limit: 10_000,
+            seed: 0x5eed,
+        }
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Longest generated input, grammar derivations may go past it to
+    /// terminate.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    pub fn step_limit(mut self, step_limit: u64) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Look for an input where the verdict of the machine differs from
+    /// `expected`, or where it does not halt.
+    pub fn check<F: Fn(&str) -> bool>(&self, expected: F) -> Result<(), Failure> {
+        self.find(|input, outcome| match (outcome.verdict, expected(input)) {
+            (Verdict::Timeout, _) => Some(Mismatch::Timeout),
+            (Verdict::Accept, false) => Some(Mismatch::FalseAccept),
+            (Verdict::Reject, true) => Some(Mismatch::FalseReject),
+            _ => None,
+        })
+    }
+
+    /// Look for an input the machine does not halt on.
+    pub fn check_halts(&self) -> Result<(), Failure> {
+        self.find(|_, outcome| match outcome.verdict {
+            Verdict::Timeout => Some(Mismatch::Timeout),
+            _ => None,
+        })
+    }
+
+    /// [`check`](Self::check), panicking with the shrunk input on failure.
+    pub fn assert<F: Fn(&str) -> bool>(&self, expected: F) {
+        if let Err(failure) = self.check(expected) {
+            panic!("{}", failure);
+        }
+    }
+
+    fn find<F>(&self, classify: F) -> Result<(), Failure>
+    where
+        F: Fn(&str, RunOutcome) -> Option<Mismatch>,
+    {
+        let mut rng = Rng(self.seed);
+        let mut runner = Runner::with_tm(self.tm);
+        let mut run = |input: &str| {
+            runner.feed_str(input);
+            let outcome = runner.run(self.step_limit);
+            classify(input, outcome).map(|mismatch| Counterexample {
+                input: input.to_string(),
+                mismatch,
+                steps: outcome.steps,
+            })
+        };
+
+        for case in 0..self.cases {
+            let input = self.generate(&mut rng);
+            let original = match run(&input) {
+                Some(c) => c,
+                None => continue,
+            };
+            let mut shrunk = original.clone();
+            while let Some(smaller) = self
+                .shrink(&shrunk.input)
+                .into_iter()
+                .filter_map(|s| run(&s))
+                .find(|c| c.mismatch == shrunk.mismatch)
+            {
+                shrunk = smaller;
+            }
+            return Err(Failure {
+                original,
+                shrunk,
+                seed: self.seed,
+                case,
+            });
+        }
+        Ok(())
+    }
+
+    fn generate(&self, rng: &mut Rng) -> String {
+        let n = self.alphabet.len();
+        let len = match &self.strategy {
+            Strategy::Grammar(g) => return g.derive(self.max_len, rng),
+            _ if n == 0 => return String::new(),
+            Strategy::LengthBiased => rng.below(self.max_len as u64 + 1) as usize,
+            Strategy::Uniform => {
+                // Length l has weight n^l, relative to the longest.
+                let weights = (0..=self.max_len)
+                    .map(|l| (n as f64).powi(l as i32 - self.max_len as i32))
+                    .collect::<Vec<_>>();
+                let mut x = rng.unit() * weights.iter().sum::<f64>();
+                weights
+                    .iter()
+                    .position(|w| {
+                        x -= w;
+                        x < 0.0
+                    })
+                    .unwrap_or(self.max_len)
+            }
+        };
+        (0..len)
+            .map(|_| self.alphabet[rng.below(n as u64) as usize])
+            .collect()
+    }
+
+    /// Smaller candidates: chunks removed, largest first, then symbols
+    /// replaced by the first symbol of the alphabet.
+    fn shrink(&self, input: &str) -> Vec<String> {
+        let syms = input.chars().collect::<Vec<_>>();
+        let mut candidates = Vec::new();
+        let mut size = syms.len();
+        while size > 0 {
+            for start in (0..=syms.len() - size).step_by(size) {
+                let mut s = syms[..start].to_vec();
+                s.extend_from_slice(&syms[start + size..]);
+                candidates.push(s.into_iter().collect());
+            }
+            size /= 2;
+        }
+        if let Some(first) = self.alphabet.first() {
+            for (i, sym) in syms.iter().enumerate() {
+                if sym != first {
+                    let mut s = syms.clone();
+                    s[i] = *first;
+                    candidates.push(s.into_iter().collect());
+                }
+            }
+        }
+        candidates
+    }
+}
+
+/// splitmix64.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`, `n` must not be 0.
+    fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, tm_bar};
+
+    fn a_n_b_n(input: &str) -> bool {
+        let n = input.len() / 2;
+        n > 0 && input == "a".repeat(n) + &"b".repeat(n)
+    }
+
+    /// [`tm_bar`] with one more transition.
+    fn tm_bar_with(transition: &str) -> TM {
+        let tm = crate::parse::to_tm(&tm_bar()).unwrap();
+        let first = "(q0, a) -> (q1, x, R)";
+        parse(tm.replace(first, &format!("{}, {}", first, transition))).unwrap()
+    }
+
+    #[test]
+    fn correct_machine_passes() {
+        let tm = tm_bar();
+        let grammar = "S -> a<S>b | ab".parse().unwrap();
+        for strategy in [
+            Strategy::Uniform,
+            Strategy::LengthBiased,
+            Strategy::Grammar(grammar),
+        ] {
+            let fuzzer = Fuzzer::new(&tm).strategy(strategy).cases(200);
+            assert_eq!(fuzzer.check(a_n_b_n), Ok(()));
+            assert_eq!(fuzzer.check_halts(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn shrinks_a_false_accept() {
+        let tm = tm_bar_with("(q0, b) -> (q4, b, S)");
+        let fuzzer = Fuzzer::new(&tm).seed(7);
+        let failure = fuzzer.check(a_n_b_n).unwrap_err();
+        assert_eq!(failure.seed, 7);
+        assert_eq!(failure.original.mismatch, Mismatch::FalseAccept);
+        assert!(failure.original.input.starts_with('b'), "{}", failure);
+        assert_eq!(
+            failure.shrunk,
+            Counterexample {
+                input: "b".to_string(),
+                mismatch: Mismatch::FalseAccept,
+                steps: 1,
+            }
+        );
+        assert_eq!(fuzzer.check(a_n_b_n), Err(failure));
+    }
+
+    #[test]
+    fn shrinks_a_timeout() {
+        let tm = tm_bar_with("(q0, b) -> (q0, b, S)");
+        let failure = Fuzzer::new(&tm).step_limit(50).check_halts().unwrap_err();
+        assert_eq!(failure.original.mismatch, Mismatch::Timeout);
+        assert_eq!(
+            failure.shrunk,
+            Counterexample {
+                input: "b".to_string(),
+                mismatch: Mismatch::Timeout,
+                steps: 50,
+            }
+        );
+    }
+
+    #[test]
+    fn uniform_words_are_mostly_long() {
+        let tm = tm_bar();
+        let fuzzer = Fuzzer::new(&tm).strategy(Strategy::Uniform).max_len(8);
+        let mut rng = Rng(fuzzer.seed);
+        let lens = (0..1000)
+            .map(|_| fuzzer.generate(&mut rng).len())
+            .collect::<Vec<_>>();
+        assert!(lens.iter().all(|l| *l <= 8));
+        // Lengths 7 and 8 are 3/4 of the words.
+        let long = lens.iter().filter(|l| **l >= 7).count();
+        assert!((700..800).contains(&long), "{}", long);
+    }
+
+    #[test]
+    fn parses_grammars() {
+        let grammar = "<S> -> a<S>b | <E>; E ->".parse::<Grammar>().unwrap();
+        assert_eq!(grammar.start, "S");
+        let tm = tm_bar();
+        let fuzzer = Fuzzer::new(&tm).strategy(Strategy::Grammar(grammar));
+        let mut rng = Rng(fuzzer.seed);
+        for _ in 0..100 {
+            let word = fuzzer.generate(&mut rng);
+            assert!(word.is_empty() || a_n_b_n(&word), "{}", word);
+        }
+
+        for (grammar, error) in [
+            ("S -> a<T>b", "no rule for <T>"),
+            ("S -> a<>b", "empty nonterminal in a<>b"),
+            ("S a", "rule without ->: S a"),
+            ("\n;", "empty grammar"),
+        ] {
+            assert_eq!(grammar.parse::<Grammar>().unwrap_err(), error);
+        }
+    }
+}
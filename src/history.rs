@@ -0,0 +1,264 @@
+//! Computation histories in the textbook notation `u q v ⊢ u' q' v'`, where
+//! the head is on the first symbol of `v`.
+use crate::runner::{Config, Trace};
+use crate::tm::Symbol;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Notation {
+    /// `xa q1 bB |- xaa q1 bB`.
+    Plain,
+    /// `xa q₁ b⊔ ⊢ xaa q₁ b⊔`.
+    Unicode,
+    /// `xa\,q_{1}\,b\sqcup \vdash xaa\,q_{1}\,b\sqcup`, in math mode.
+    Latex,
+}
+
+/// Formatter of the configurations of a [`Trace`].
+pub struct History<'a> {
+    trace: &'a Trace,
+    empty_sym: Symbol,
+    notation: Notation,
+    trim: bool,
+    width: Option<usize>,
+}
+
+impl<'a> History<'a> {
+    pub fn new(trace: &'a Trace, empty_sym: Symbol) -> Self {
+        Self {
+            trace,
+            empty_sym,
+            notation: Notation::Plain,
+            trim: false,
+            width: None,
+        }
+    }
+
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    /// Drop empty symbols on both ends of the tape, except under the head.
+    pub fn trim_blanks(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Break lines before `⊢` to keep them within `width` characters. Plain
+    /// and unicode configurations longer than `width` are split as well, a
+    /// line may only be longer when `⊢` and its space do not fit.
+    pub fn wrap(mut self, width: usize) -> Self {
+        self.width = Some(width.max(1));
+        self
+    }
+
+    fn separator(&self) -> &'static str {
+        match self.notation {
+            Notation::Plain => "|-",
+            Notation::Unicode => "⊢",
+            Notation::Latex => "\\vdash",
+        }
+    }
+
+    fn config(&self, c: &Config) -> String {
+        let tape = c.tape.chars().collect::<Vec<_>>();
+        let (mut u, mut v) = tape.split_at(c.head.min(tape.len()));
+        if self.trim {
+            while u.first() == Some(&self.empty_sym) {
+                u = &u[1..];
+            }
+            while v.len() > 1 && v.last() == Some(&self.empty_sym) {
+                v = &v[..v.len() - 1];
+            }
+        }
+        let syms = |syms: &[Symbol]| {
+            syms.iter()
+                .map(|s| self.symbol(*s))
+                .collect::<Vec<_>>()
+                .concat()
+        };
+        let state = self.state(&c.state);
+        match self.notation {
+            Notation::Latex => format!("{}\\,{}\\,{}", syms(u), state, syms(v)),
+            _ => format!("{} {} {}", syms(u), state, syms(v))
+                .trim()
+                .to_string(),
+        }
+    }
+
+    fn symbol(&self, s: Symbol) -> String {
+        match self.notation {
+            Notation::Plain => s.to_string(),
+            Notation::Unicode if s == self.empty_sym => "⊔".to_string(),
+            Notation::Unicode => s.to_string(),
+            Notation::Latex if s == self.empty_sym => "\\sqcup ".to_string(),
+            Notation::Latex => match s {
+                '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{}", s),
+                '\\' => "\\backslash ".to_string(),
+                '~' => "\\text{\\textasciitilde}".to_string(),
+                '^' => "\\text{\\textasciicircum}".to_string(),
+                _ => s.to_string(),
+            },
+        }
+    }
+
+    /// State with its trailing digits as subscript, `q10` is `q₁₀`.
+    fn state(&self, s: &str) -> String {
+        let digits = s.len() - s.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (name, index) = s.split_at(s.len() - digits);
+        match self.notation {
+            Notation::Plain => s.to_string(),
+            Notation::Unicode => {
+                let sub = index
+                    .chars()
+                    .map(|d| std::char::from_u32('₀' as u32 + d.to_digit(10).unwrap()).unwrap());
+                name.chars().chain(sub).collect()
+            }
+            Notation::Latex if index.is_empty() => format!("\\mathit{{{}}}", name),
+            Notation::Latex => format!("\\mathit{{{}}}_{{{}}}", name, index),
+        }
+    }
+}
+
+impl fmt::Display for History<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = self.separator();
+        let configs = self.trace.configs.iter().map(|c| self.config(c));
+        let width = match self.width {
+            None => {
+                let configs = configs.collect::<Vec<_>>();
+                return write!(f, "{}", configs.join(&format!(" {} ", sep)));
+            }
+            Some(width) => width,
+        };
+
+        let split = self.notation != Notation::Latex;
+        let line_end = if split { "" } else { " \\\\" };
+        let mut line = 0;
+        for (i, config) in configs.enumerate() {
+            let len = config.chars().count();
+            if i != 0 {
+                if line + sep.chars().count() + len + 2 > width {
+                    writeln!(f, "{}", line_end)?;
+                    write!(f, "{} ", sep)?;
+                    line = sep.chars().count() + 1;
+                } else {
+                    write!(f, " {} ", sep)?;
+                    line += sep.chars().count() + 2;
+                }
+            }
+            if split && line + len > width {
+                let chars = config.chars().collect::<Vec<_>>();
+                let mut rest = &chars[..];
+                while line + rest.len() > width {
+                    let (head, tail) = rest.split_at(width.saturating_sub(line));
+                    writeln!(f, "{}", head.iter().collect::<String>())?;
+                    rest = tail;
+                    line = 0;
+                }
+                write!(f, "{}", rest.iter().collect::<String>())?;
+                line += rest.len();
+            } else {
+                write!(f, "{}", config)?;
+                line += len;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tm_bar, Runner};
+
+    fn trace() -> Trace {
+        Runner::with_tm(&tm_bar()).trace("ab", 100)
+    }
+
+    #[test]
+    fn plain() {
+        let trace = trace();
+        let history = History::new(&trace, 'B');
+        assert_eq!(
+            history.to_string(),
+            "B q0 abB |- Bx q1 bB |- B q2 xyB |- Bx q0 yB |- Bxy q3 B |- Bx q4 yB"
+        );
+        assert_eq!(
+            history.trim_blanks(true).to_string(),
+            "q0 ab |- x q1 b |- q2 xy |- x q0 y |- xy q3 B |- x q4 y"
+        );
+    }
+
+    #[test]
+    fn unicode() {
+        let trace = trace();
+        let history = History::new(&trace, 'B').notation(Notation::Unicode);
+        assert_eq!(
+            history.to_string(),
+            "⊔ q₀ ab⊔ ⊢ ⊔x q₁ b⊔ ⊢ ⊔ q₂ xy⊔ ⊢ ⊔x q₀ y⊔ ⊢ ⊔xy q₃ ⊔ ⊢ ⊔x q₄ y⊔"
+        );
+        assert_eq!(
+            history.trim_blanks(true).wrap(12).to_string(),
+            "q₀ ab\n⊢ x q₁ b\n⊢ q₂ xy\n⊢ x q₀ y\n⊢ xy q₃ ⊔\n⊢ x q₄ y"
+        );
+    }
+
+    #[test]
+    fn latex() {
+        let trace = trace();
+        let history = History::new(&trace, 'B')
+            .notation(Notation::Latex)
+            .trim_blanks(true);
+        assert_eq!(
+            history.to_string(),
+            r"\,\mathit{q}_{0}\,ab \vdash x\,\mathit{q}_{1}\,b \vdash \,\mathit{q}_{2}\,xy \vdash x\,\mathit{q}_{0}\,y \vdash xy\,\mathit{q}_{3}\,\sqcup  \vdash x\,\mathit{q}_{4}\,y"
+        );
+        // Configurations are not split, lines end with `\\`.
+        assert_eq!(
+            history.wrap(30).to_string(),
+            r"\,\mathit{q}_{0}\,ab \\
+\vdash x\,\mathit{q}_{1}\,b \\
+\vdash \,\mathit{q}_{2}\,xy \\
+\vdash x\,\mathit{q}_{0}\,y \\
+\vdash xy\,\mathit{q}_{3}\,\sqcup  \\
+\vdash x\,\mathit{q}_{4}\,y"
+        );
+    }
+
+    #[test]
+    fn wrap_keeps_lines_within_the_width() {
+        let trace = trace();
+        let history = || History::new(&trace, 'B');
+        // The first two configurations take exactly 20 characters.
+        assert_eq!(
+            history().wrap(20).to_string(),
+            "B q0 abB |- Bx q1 bB\n|- B q2 xyB\n|- Bx q0 yB\n|- Bxy q3 B\n|- Bx q4 yB"
+        );
+        assert_eq!(
+            history().wrap(19).to_string(),
+            "B q0 abB\n|- Bx q1 bB\n|- B q2 xyB\n|- Bx q0 yB\n|- Bxy q3 B\n|- Bx q4 yB"
+        );
+        // Configurations longer than the width are split.
+        let wrapped = history().wrap(5).to_string();
+        assert!(
+            wrapped.starts_with("B q0 \nabB\n|- Bx\n q1 b\nB\n"),
+            "{}",
+            wrapped
+        );
+        for width in 3..30 {
+            let wrapped = history().wrap(width).to_string();
+            assert!(
+                wrapped.lines().all(|l| l.chars().count() <= width),
+                "{}",
+                wrapped
+            );
+            // Nothing is lost but the spaces before line breaks.
+            assert_eq!(
+                wrapped.replace('\n', "").replace(" |- ", "|- "),
+                history().to_string().replace(" |- ", "|- ")
+            );
+        }
+    }
+}
@@ -1,12 +1,33 @@
 #[macro_use]
 extern crate pest_derive;
 
+pub mod compose;
+mod diagram;
+pub mod formats;
+pub mod fuzz;
+pub mod history;
+pub mod lint;
+pub mod macro_runner;
 pub mod parse;
+pub mod render;
 pub mod runner;
+#[cfg(feature = "serde")]
+pub mod serde_impl;
+pub mod stdlib;
+pub mod tape;
+pub mod test_case;
+#[cfg(test)]
+mod test_util;
 pub mod tm;
+pub mod transform;
+pub mod verify;
 
+pub use history::*;
+pub use macro_runner::*;
 pub use parse::*;
 pub use runner::*;
+pub use tape::*;
+pub use test_case::*;
 pub use tm::*;
 
 pub fn tm_foo() -> TM {
@@ -0,0 +1,250 @@
+//! Static checks of machine definitions, for mistakes that still build.
+//!
+//! Warnings of a `.tm` file are silenced by listing their codes in an
+//! `Allow` section after `Empty`, e.g. `Allow = {W002, W004}`.
+use crate::parse::Document;
+use crate::tm::{State, Symbol, TM};
+
+use rustc_hash::FxHashSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Kind of a [`Warning`], each with a stable code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Lint {
+    /// `W001`: a state no run from the start state enters.
+    UnreachableState,
+    /// `W002`: a reachable state without transitions that is not accepting,
+    /// the machine rejects once there.
+    ImplicitReject,
+    /// `W003`: a transition out of an accept state, the machine halts before
+    /// it could fire.
+    AcceptTransition,
+    /// `W004`: a tape symbol no transition reads or writes.
+    UnusedTapeSymbol,
+    /// `W005`: an input symbol the start state has no transition on, inputs
+    /// starting with it are rejected right away.
+    UnhandledInputSymbol,
+    /// `W006`: an accept state no run from the start state enters, the
+    /// machine accepts nothing through it.
+    UnreachableAccept,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnreachableState,
+        Lint::ImplicitReject,
+        Lint::AcceptTransition,
+        Lint::UnusedTapeSymbol,
+        Lint::UnhandledInputSymbol,
+        Lint::UnreachableAccept,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnreachableState => "W001",
+            Lint::ImplicitReject => "W002",
+            Lint::AcceptTransition => "W003",
+            Lint::UnusedTapeSymbol => "W004",
+            Lint::UnhandledInputSymbol => "W005",
+            Lint::UnreachableAccept => "W006",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|l| l.code() == s)
+            .ok_or_else(|| format!("unknown lint: {}", s))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    pub lint: Lint,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.lint, self.message)
+    }
+}
+
+/// Every warning of `tm`, ordered by code, then by state or symbol.
+pub fn lint(tm: &TM) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut warn = |lint: Lint, message: String| warnings.push(Warning { lint, message });
+    let reachable = reachable(tm);
+    let mut states = tm.states().collect::<Vec<_>>();
+    states.sort();
+    let mut fns = tm.transfer_fns().collect::<Vec<_>>();
+    fns.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    for s in states.iter() {
+        if !reachable.contains(*s) && !tm.accept(s) {
+            warn(
+                Lint::UnreachableState,
+                format!("state {} is unreachable from {}", s, tm.start_state()),
+            );
+        }
+    }
+    for s in states.iter() {
+        if reachable.contains(*s) && !tm.accept(s) && !fns.iter().any(|f| f.0 == *s) {
+            warn(
+                Lint::ImplicitReject,
+                format!("state {} has no transitions and is not accepting", s),
+            );
+        }
+    }
+    for (s0, sym0, ..) in fns.iter() {
+        if tm.accept(s0) && *s0 != tm.start_state() {
+            warn(
+                Lint::AcceptTransition,
+                format!(
+                    "transition on ({}, {}) leaves accept state {}",
+                    s0, sym0, s0
+                ),
+            );
+        }
+    }
+
+    let used = fns
+        .iter()
+        .flat_map(|(_, sym0, _, sym1, _)| Some(*sym0).into_iter().chain(*sym1))
+        .collect::<FxHashSet<_>>();
+    for sym in sorted(tm.tape_syms()) {
+        if !used.contains(&sym) && sym != tm.empty_sym() {
+            warn(
+                Lint::UnusedTapeSymbol,
+                format!("tape symbol {} is never read or written", sym),
+            );
+        }
+    }
+    for sym in sorted(tm.syms()) {
+        let start = State::clone(tm.start_state());
+        if sym != tm.empty_sym() && tm.transfer(start, sym).is_none() {
+            warn(
+                Lint::UnhandledInputSymbol,
+                format!(
+                    "start state {} has no transition on input symbol {}",
+                    tm.start_state(),
+                    sym
+                ),
+            );
+        }
+    }
+    for s in states.iter() {
+        if !reachable.contains(*s) && tm.accept(s) {
+            warn(
+                Lint::UnreachableAccept,
+                format!(
+                    "accept state {} is unreachable from {}",
+                    s,
+                    tm.start_state()
+                ),
+            );
+        }
+    }
+    warnings
+}
+
+impl Document {
+    /// Warnings of the machine, without the ones its `Allow` section lists.
+    pub fn lint(&self) -> Vec<Warning> {
+        lint(&self.tm)
+            .into_iter()
+            .filter(|w| !self.allow.contains(&w.lint))
+            .collect()
+    }
+}
+
+/// States some run from the start state enters. Runs halt on entering an
+/// accept state, so paths go on only from the start state and states that
+/// are not accepting, like in [`prune`](crate::transform::prune).
+fn reachable(tm: &TM) -> FxHashSet<&State> {
+    let mut seen = FxHashSet::default();
+    let mut todo = vec![tm.start_state()];
+    while let Some(s) = todo.pop() {
+        if seen.insert(s) && (!tm.accept(s) || s == tm.start_state()) {
+            todo.extend(
+                tm.transfer_fns()
+                    .filter(|f| f.0 == s)
+                    .map(|(_, _, s1, _, _)| s1),
+            );
+        }
+    }
+    seen
+}
+
+fn sorted(syms: impl Iterator<Item = Symbol>) -> Vec<Symbol> {
+    let mut syms = syms.collect::<Vec<_>>();
+    syms.sort_unstable();
+    syms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn codes(tm: &str) -> Vec<(&'static str, String)> {
+        lint(&parse(tm).unwrap())
+            .into_iter()
+            .map(|w| (w.lint.code(), w.message))
+            .collect()
+    }
+
+    #[test]
+    fn states_after_an_accept_state_are_unreachable() {
+        let warnings = codes(
+            "StateSet = {q0, q1, q2}
+            SymbolSet = {a}
+            FnSet = {(q0, a) -> (q1, a, R), (q1, a) -> (q2, a, R)}
+            Start = q0
+            FinalSet = {q1}",
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                ("W001", "state q2 is unreachable from q0".to_string()),
+                (
+                    "W003",
+                    "transition on (q1, a) leaves accept state q1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepting_start_state_runs() {
+        let warnings = codes(
+            "StateSet = {q0, q1}
+            SymbolSet = {a}
+            FnSet = {(q0, a) -> (q1, a, R), (q1, a) -> (q0, a, R)}
+            Start = q0
+            FinalSet = {q0}",
+        );
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn allow_silences_warnings() {
+        let doc = crate::parse_document(include_str!("../examples/a_n_b_n.tm")).unwrap();
+        assert!(doc.lint().is_empty());
+        assert_eq!(lint(&doc.tm)[0].lint, Lint::UnhandledInputSymbol);
+    }
+}
@@ -0,0 +1,407 @@
+use crate::runner::{RunOutcome, RunnerState, Verdict};
+use crate::tm::{HeadDirection, State, Symbol, TM};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+type BlockId = usize;
+
+/// Id of the block made of empty symbols only.
+const BLANK: BlockId = 0;
+
+/// Side of a block the head enters or leaves through.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+enum Side {
+    Left,
+    Right,
+}
+
+/// Effect of running the machine inside a single block.
+#[derive(Debug, Clone, Copy)]
+enum MacroTransfer {
+    /// Head left the block through `side`.
+    Exit {
+        block: BlockId,
+        state: usize,
+        side: Side,
+        steps: u64,
+    },
+    /// Machine halted inside the block.
+    Halt {
+        block: BlockId,
+        state: usize,
+        verdict: Verdict,
+        steps: u64,
+    },
+    /// Head never leaves the block and the machine never halts.
+    Loop,
+}
+
+/// Accelerated runner which simulates a turing machine on blocks of
+/// `block_size` cells.
+///
+/// The tape is kept as runs of identical blocks, the effect of entering a
+/// block from one side is memoised, and a run of blocks the head sweeps
+/// through without changing state is processed in one go. Outcome and
+/// step count match the ones of [`Runner::run`](crate::Runner::run).
+pub struct MacroRunner<'a> {
+    tm: &'a TM,
+    block_size: usize,
+    states: Vec<State>,
+    accept: Vec<bool>,
+    start: usize,
+    table: FxHashMap<(usize, Symbol), (usize, Symbol, HeadDirection)>,
+    blocks: Vec<Box<[Symbol]>>,
+    block_ids: FxHashMap<Box<[Symbol]>, BlockId>,
+    cache: FxHashMap<(usize, BlockId, Side), MacroTransfer>,
+    // Runs of blocks on each side of the head, the nearest run is the last.
+    left: Vec<(BlockId, u64)>,
+    right: Vec<(BlockId, u64)>,
+    current_state: usize,
+    // Head sits on a block boundary, facing the nearest block of one side.
+    facing: Side,
+    steps: u64,
+    runner_state: RunnerState,
+}
+
+impl<'a> MacroRunner<'a> {
+    pub fn with_tm(tm: &'a TM, block_size: usize) -> Self {
+        assert!(block_size > 0, "block size must be positive");
+
+        let states = tm.states().cloned().collect::<Vec<_>>();
+        let ids = states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (State::clone(s), i))
+            .collect::<FxHashMap<_, _>>();
+        let accept = states.iter().map(|s| tm.accept(s)).collect();
+        let table = tm
+            .transfer_fns()
+            .map(|(s0, sym0, s1, sym1, dir)| {
+                ((ids[s0], sym0), (ids[s1], sym1.unwrap_or(sym0), dir))
+            })
+            .collect();
+
+        let blank: Box<[Symbol]> = vec![tm.empty_sym(); block_size].into();
+        let mut block_ids = FxHashMap::default();
+        block_ids.insert(blank.clone(), BLANK);
+
+        Self {
+            tm,
+            block_size,
+            start: ids[tm.start_state()],
+            states,
+            accept,
+            table,
+            blocks: vec![blank],
+            block_ids,
+            cache: FxHashMap::default(),
+            left: Vec::new(),
+            right: Vec::new(),
+            current_state: 0,
+            facing: Side::Right,
+            steps: 0,
+            runner_state: RunnerState::Hungry,
+        }
+    }
+
+    /// Load input, memoised block transfers are kept between inputs.
+    pub fn feed_str<T: AsRef<str>>(&mut self, input_str: T) {
+        let syms = input_str.as_ref().chars().collect::<Vec<_>>();
+        let empty_sym = self.tm.empty_sym();
+
+        self.left.clear();
+        self.right.clear();
+        for chunk in syms.chunks(self.block_size).rev() {
+            let mut block = chunk.to_vec();
+            block.resize(self.block_size, empty_sym);
+            let id = self.intern(block);
+            push_run(&mut self.right, id, 1);
+        }
+        self.current_state = self.start;
+        self.facing = Side::Right;
+        self.steps = 0;
+        self.runner_state = RunnerState::Running;
+    }
+
+    /// Run until the machine halts or `limit` transitions have been taken.
+    ///
+    /// On timeout the runner stays on the last block boundary before
+    /// `limit`, so `run` can be called again with a larger limit.
+    pub fn run(&mut self, limit: u64) -> RunOutcome {
+        while self.runner_state == RunnerState::Running {
+            let budget = limit.saturating_sub(self.steps);
+            let facing = self.facing;
+            let (block, count) = self.stack(facing).last().copied().unwrap_or((BLANK, 0));
+
+            match self.macro_transfer(self.current_state, block, facing) {
+                MacroTransfer::Loop => return self.timeout(limit),
+                MacroTransfer::Halt {
+                    block: next,
+                    state,
+                    verdict,
+                    steps,
+                } => {
+                    if steps > budget {
+                        return self.timeout(limit);
+                    }
+                    pop_block(self.stack_mut(facing));
+                    push_run(&mut self.right, next, 1);
+                    self.facing = Side::Right;
+                    self.current_state = state;
+                    self.steps += steps;
+                    self.runner_state = match verdict {
+                        Verdict::Accept => RunnerState::Accept,
+                        _ => RunnerState::Reject,
+                    };
+                }
+                MacroTransfer::Exit {
+                    block: next,
+                    state,
+                    side,
+                    steps,
+                } if side == facing && state == self.current_state => {
+                    // Every block of the run is swept the same way.
+                    if count == 0 {
+                        // endless empty tape, the head never comes back.
+                        return self.timeout(limit);
+                    }
+                    let total = steps.saturating_mul(count);
+                    if total > budget {
+                        return self.timeout(limit);
+                    }
+                    self.stack_mut(facing).pop();
+                    push_run(self.stack_mut(opposite(facing)), next, count);
+                    self.steps += total;
+                }
+                MacroTransfer::Exit {
+                    block: next,
+                    state,
+                    side,
+                    steps,
+                } => {
+                    if steps > budget {
+                        return self.timeout(limit);
+                    }
+                    pop_block(self.stack_mut(facing));
+                    push_run(self.stack_mut(opposite(side)), next, 1);
+                    self.facing = side;
+                    self.current_state = state;
+                    self.steps += steps;
+                }
+            }
+        }
+
+        let verdict = match self.runner_state {
+            RunnerState::Accept => Verdict::Accept,
+            RunnerState::Reject => Verdict::Reject,
+            _ => Verdict::Timeout,
+        };
+        RunOutcome {
+            verdict,
+            steps: self.steps,
+        }
+    }
+
+    pub fn runner_state(&self) -> RunnerState {
+        self.runner_state
+    }
+
+    pub fn current_state(&self) -> &State {
+        &self.states[self.current_state]
+    }
+
+    /// Content of the tape, without the empty symbols on both ends.
+    pub fn tape_str(&self) -> String {
+        let left = self.left.iter();
+        let right = self.right.iter().rev();
+        let tape = left
+            .chain(right)
+            .flat_map(|(id, n)| (0..*n).flat_map(move |_| self.blocks[*id].iter().copied()))
+            .collect::<String>();
+        tape.trim_matches(self.tm.empty_sym()).to_string()
+    }
+
+    fn timeout(&self, limit: u64) -> RunOutcome {
+        RunOutcome {
+            verdict: Verdict::Timeout,
+            steps: limit,
+        }
+    }
+
+    fn stack(&self, side: Side) -> &Vec<(BlockId, u64)> {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+        }
+    }
+
+    fn stack_mut(&mut self, side: Side) -> &mut Vec<(BlockId, u64)> {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+        }
+    }
+
+    fn intern(&mut self, block: Vec<Symbol>) -> BlockId {
+        let block = block.into_boxed_slice();
+        if let Some(id) = self.block_ids.get(&block) {
+            return *id;
+        }
+        let id = self.blocks.len();
+        self.blocks.push(block.clone());
+        self.block_ids.insert(block, id);
+        id
+    }
+
+    fn macro_transfer(&mut self, state: usize, block: BlockId, facing: Side) -> MacroTransfer {
+        if let Some(t) = self.cache.get(&(state, block, facing)) {
+            return *t;
+        }
+        let t = self.simulate(state, block, facing);
+        self.cache.insert((state, block, facing), t);
+        t
+    }
+
+    /// Run the machine inside `block`, entered while facing `facing`.
+    fn simulate(&mut self, mut state: usize, block: BlockId, facing: Side) -> MacroTransfer {
+        let k = self.block_size;
+        let mut cells = self.blocks[block].to_vec();
+        let mut pos = match facing {
+            Side::Right => 0,
+            Side::Left => k - 1,
+        };
+        let mut steps = 0;
+        let mut seen = FxHashSet::default();
+
+        loop {
+            if !seen.insert((state, pos, cells.clone())) {
+                return MacroTransfer::Loop;
+            }
+            let (next_state, next_sym, dir) = match self.table.get(&(state, cells[pos])) {
+                Some(t) => *t,
+                None => {
+                    return MacroTransfer::Halt {
+                        block: self.intern(cells),
+                        state,
+                        verdict: Verdict::Reject,
+                        steps,
+                    }
+                }
+            };
+            cells[pos] = next_sym;
+            state = next_state;
+            steps += 1;
+
+            let exit = match dir {
+                HeadDirection::Left if pos == 0 => Some(Side::Left),
+                HeadDirection::Right if pos + 1 == k => Some(Side::Right),
+                HeadDirection::Left => {
+                    pos -= 1;
+                    None
+                }
+                HeadDirection::Right => {
+                    pos += 1;
+                    None
+                }
+                HeadDirection::Stop => None,
+            };
+            if self.accept[state] {
+                return MacroTransfer::Halt {
+                    block: self.intern(cells),
+                    state,
+                    verdict: Verdict::Accept,
+                    steps,
+                };
+            }
+            if let Some(side) = exit {
+                return MacroTransfer::Exit {
+                    block: self.intern(cells),
+                    state,
+                    side,
+                    steps,
+                };
+            }
+        }
+    }
+}
+
+fn opposite(side: Side) -> Side {
+    match side {
+        Side::Left => Side::Right,
+        Side::Right => Side::Left,
+    }
+}
+
+fn pop_block(stack: &mut Vec<(BlockId, u64)>) {
+    if let Some((_, n)) = stack.last_mut() {
+        *n -= 1;
+        if *n == 0 {
+            stack.pop();
+        }
+    }
+}
+
+fn push_run(stack: &mut Vec<(BlockId, u64)>, block: BlockId, n: u64) {
+    match stack.last_mut() {
+        Some((top, m)) if *top == block => *m += n,
+        // empty blocks at the end of the tape are implicit.
+        None if block == BLANK => (),
+        _ => stack.push((block, n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, test_util, verify::Words, Runner};
+
+    /// Outcome and tape of `MacroRunner` match the ones of `Runner` for every
+    /// input and block size.
+    fn assert_matches_runner(tm: &TM, inputs: &[String], limit: u64) {
+        for block_size in 1..=4 {
+            let mut macro_runner = MacroRunner::with_tm(tm, block_size);
+            for input in inputs {
+                let mut runner = Runner::with_tm(tm);
+                runner.feed_str(input);
+                let expected = runner.run(limit);
+                macro_runner.feed_str(input);
+                let outcome = macro_runner.run(limit);
+                let context = format!("{:?} in blocks of {}", input, block_size);
+                assert_eq!(outcome, expected, "{}", context);
+                if outcome.verdict != Verdict::Timeout {
+                    let tape = runner.ir().tape_str();
+                    let tape = tape.trim_matches(tm.empty_sym());
+                    assert_eq!(macro_runner.tape_str(), tape, "{}", context);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn matches_runner_on_a_n_b_n() {
+        let tm = test_util::a_n_b_n();
+        let inputs = Words::new(vec!['a', 'b'], 6).collect::<Vec<_>>();
+        assert_matches_runner(&tm, &inputs, 10_000);
+    }
+
+    #[test]
+    fn matches_runner_on_multiply() {
+        let tm = test_util::multiply();
+        let inputs = test_util::products();
+        assert_matches_runner(&tm, &inputs, 10_000);
+    }
+
+    #[test]
+    fn times_out_like_runner() {
+        let tm = parse(
+            "StateSet = {q0, q1}
+            SymbolSet = {a}
+            FnSet = {(q0, a) -> (q0, a, R), (q0, B) -> (q0, a, R)}
+            Start = q0
+            FinalSet = {q1}
+            Empty = B",
+        )
+        .unwrap();
+        let inputs = vec!["".to_string(), "aaa".to_string()];
+        assert_matches_runner(&tm, &inputs, 50);
+    }
+}
@@ -1,38 +1,96 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 use std::process::exit;
-use std::{env, fs::read_to_string};
-use turing_machine::*;
 
-fn main() {
-    let mut args = env::args().skip(1).collect::<Vec<_>>();
-    if args.len() < 2 {
-        eprintln!("turing-machine  [-v] file input");
-        exit(1)
-    }
+mod cli;
 
-    let mut verbose = false;
-    if args.len() == 3 && args[0] == "-v" {
-        verbose = true;
-        args.remove(0);
-    }
+/// Run, check and convert turing machines.
+///
+/// Exit codes: 0 accept or success, 1 reject or failed check, 2 timeout,
+/// 3 invalid machine definition, 4 I/O error, 64 invalid arguments.
+#[derive(Parser)]
+#[command(name = "turing-machine", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-    let tm_file = &args[0];
-    let input = &args[1];
+#[derive(Subcommand)]
+enum Command {
+    /// Run a machine on an input.
+    Run(cli::run::RunArgs),
+    /// Print the computation history of a run.
+    Trace(cli::run::TraceArgs),
+    /// Validate a machine definition.
+    Check(cli::check::CheckArgs),
+    /// Print a machine definition in the canonical layout of its format.
+    Fmt(cli::fmt::FmtArgs),
+    /// Convert a machine definition to another format.
+    Convert(cli::fmt::ConvertArgs),
+    /// Print the state diagram.
+    Dot {
+        /// Machine definition, the format follows the extension.
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        syntax: Syntax,
+    },
+    /// Check the verdicts of a machine on some inputs.
+    Test(cli::test::TestArgs),
+    /// Run a machine on random inputs and shrink the failing ones.
+    Fuzz(cli::fuzz::FuzzArgs),
+    /// Compare two machines: their definitions and verdicts on short inputs.
+    Diff(cli::diff::DiffArgs),
+    /// Step through a run in an interactive terminal UI.
+    Debug {
+        /// Machine definition, the format follows the extension.
+        file: PathBuf,
+        input: String,
+    },
+    /// Load a machine and run commands against it.
+    Repl {
+        /// Machine definition, the format follows the extension.
+        file: PathBuf,
+    },
+}
 
-    let tm_def = read_to_string(&tm_file).unwrap_or_else(|e| {
-        eprintln!("Error: failed to read {}: {}", tm_file, e);
-        exit(1)
-    });
-    let tm = parse(&tm_def).unwrap_or_else(|e| {
-        eprintln!("Error: invalid defination of turing machine.\n{}", e);
-        exit(1)
-    });
+#[derive(Debug, Clone, Copy, Default, clap::ValueEnum)]
+enum Syntax {
+    #[default]
+    Dot,
+    Mermaid,
+    Tikz,
+}
 
-    let mut runner = Runner::with_tm(&tm);
-    runner.feed_str(input);
-    while runner.step() == RunnerState::Running {
-        if verbose {
-            println!("{}", runner.ir());
-        }
-    }
-    println!("{}", runner.ir());
-}
\ No newline at end of file
+fn main() {
+    let args = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        exit(if e.use_stderr() { cli::USAGE } else { 0 })
+    });
+    let code = match args.command {
+        Command::Run(args) => cli::run::run(args),
+        Command::Trace(args) => cli::run::trace(args),
+        Command::Check(args) => cli::check::check(args),
+        Command::Fmt(args) => cli::fmt::fmt(args),
+        Command::Convert(args) => cli::fmt::convert(args),
+        Command::Dot { file, syntax } => cli::load_tm(&file).map(|tm| {
+            match syntax {
+                Syntax::Dot => print!("{}", tm.to_dot()),
+                Syntax::Mermaid => print!("{}", tm.to_mermaid()),
+                Syntax::Tikz => print!("{}", tm.to_tikz()),
+            }
+            cli::ACCEPT
+        }),
+        Command::Test(args) => cli::test::test(args),
+        Command::Fuzz(args) => cli::fuzz::fuzz(args),
+        Command::Diff(args) => cli::diff::diff(args),
+        Command::Debug { file, input } => cli::load_tm_for(&file, &[&input]).and_then(|tm| {
+            cli::debug::run(&tm, &file.display().to_string(), &input)?;
+            Ok(cli::ACCEPT)
+        }),
+        Command::Repl { file } => cli::repl::run(&file),
+    };
+    exit(code.unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        e.exit_code()
+    }))
+}
@@ -1,21 +1,44 @@
 use crate::TM;
 use crate::{
-    tm::{Symbol, TMBuilder, TransferFnItem},
+    lint::Lint,
+    runner::Verdict,
+    test_case::{Expect, TestCase},
+    tm::{State, Symbol, TMBuilder, TransferFnItem},
     HeadDirection,
 };
 
 use pest::{iterators::Pair, Parser};
 use rustc_hash::FxHashSet;
+use std::fmt::Write;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("parse: {0}")]
-    Parse(#[from] pest::error::Error<Rule>),
+    Parse(Box<pest::error::Error<Rule>>),
     #[error("semanic: {0}")]
     Semantic(String),
 }
 
+impl From<pest::error::Error<Rule>> for Error {
+    fn from(e: pest::error::Error<Rule>) -> Self {
+        Error::Parse(Box::new(e))
+    }
+}
+
+/// Content of a `.tm` file: the machine, its test cases and the lints it
+/// silences.
+pub struct Document {
+    pub tm: TM,
+    pub tests: Vec<TestCase>,
+    pub allow: Vec<Lint>,
+}
+
 pub fn parse<T: AsRef<str>>(content: T) -> Result<TM, Error> {
+    parse_document(content).map(|d| d.tm)
+}
+
+/// Parse a `.tm` file along with its optional `Allow` and `Tests` sections.
+pub fn parse_document<T: AsRef<str>>(content: T) -> Result<Document, Error> {
     let content = content.as_ref();
     let mut pt = TMParser::parse(Rule::ROOT, content)?;
 
@@ -72,12 +95,23 @@ pub fn parse<T: AsRef<str>>(content: T) -> Result<TM, Error> {
     }
 
     let mut empty = 'B';
-    if let Some(p) = pt.next() {
-        if p.as_rule() == Rule::Empty {
-            empty = parse_symbol(p.into_inner().next().unwrap());
+    let mut tests = Vec::new();
+    let mut allow = Vec::new();
+    for p in pt {
+        match p.as_rule() {
+            Rule::Empty => empty = parse_symbol(p.into_inner().next().unwrap()),
+            Rule::Allow => {
+                allow = p
+                    .into_inner()
+                    .map(|p| p.as_str().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(Error::Semantic)?
+            }
+            Rule::Tests => tests = p.into_inner().map(parse_test).collect::<Result<_, _>>()?,
+            _ => {}
         }
     }
-    TMBuilder::new()
+    let tm = TMBuilder::new()
         .states(states)
         .syms(sym_set)
         .tape_syms(tsym_set)
@@ -86,7 +120,208 @@ pub fn parse<T: AsRef<str>>(content: T) -> Result<TM, Error> {
         .empty_sym(empty)
         .transfer_fns(fns)
         .build()
-        .map_err(Error::Semantic)
+        .map_err(Error::Semantic)?;
+    Ok(Document { tm, tests, allow })
+}
+
+impl Document {
+    /// Write the machine like [`to_tm`] followed by the `Allow` and `Tests`
+    /// sections.
+    pub fn to_tm(&self) -> Result<String, Error> {
+        let mut out = to_tm(&self.tm)?;
+        if !self.allow.is_empty() {
+            let mut allow = self.allow.clone();
+            allow.sort();
+            allow.dedup();
+            let codes = allow.iter().map(|l| l.code()).collect::<Vec<_>>();
+            writeln!(out, "Allow = {{{}}}", codes.join(", ")).unwrap();
+        }
+        if self.tests.is_empty() {
+            return Ok(out);
+        }
+        out += "Tests = {\n";
+        for (i, t) in self.tests.iter().enumerate() {
+            let expect = match &t.expect {
+                Expect::Verdict(Verdict::Accept) => "-> accept".to_string(),
+                Expect::Verdict(Verdict::Reject) => "-> reject".to_string(),
+                Expect::Verdict(Verdict::Timeout) => "-> timeout".to_string(),
+                Expect::Output(output) => format!("=> {}", quote(output)),
+            };
+            write!(out, "    {} {}", quote(&t.input), expect).unwrap();
+            if let Some(n) = t.max_steps {
+                write!(out, " within {}", n).unwrap();
+            }
+            out += if i + 1 == self.tests.len() {
+                "\n"
+            } else {
+                ",\n"
+            };
+        }
+        out += "}\n";
+        Ok(out)
+    }
+}
+
+/// Write `tm` in the `.tm` format, states and symbols in name order with
+/// the start state first. Fails on machines the grammar can not express.
+pub fn to_tm(tm: &TM) -> Result<String, Error> {
+    let mut states = tm
+        .states()
+        .filter(|s| *s != tm.start_state())
+        .collect::<Vec<_>>();
+    states.sort();
+    states.insert(0, tm.start_state());
+    if let Some(s) = states.iter().find(|s| !is_ident(s)) {
+        return Err(Error::Semantic(format!("invalid state name: {}", s)));
+    }
+    let sorted = |syms: &mut dyn Iterator<Item = Symbol>| {
+        let mut syms = syms.collect::<Vec<_>>();
+        syms.sort_unstable();
+        syms
+    };
+    let syms = sorted(&mut tm.syms());
+    let tape_syms = sorted(&mut tm.tape_syms());
+    if let Some(s) = tape_syms.iter().find(|s| s.is_whitespace()) {
+        return Err(Error::Semantic(format!("invalid symbol: {:?}", s)));
+    }
+    let mut accept_states = tm.accept_states().collect::<Vec<_>>();
+    accept_states.sort();
+    if syms.is_empty() || accept_states.is_empty() || tm.transfer_fns().next().is_none() {
+        return Err(Error::Semantic(
+            "symbols, transitions and accept states can not be empty".to_string(),
+        ));
+    }
+
+    let mut fns = tm.transfer_fns().collect::<Vec<_>>();
+    let order = |s: &State| states.iter().position(|s1| *s1 == s);
+    fns.sort_by_key(|(s, sym, _, _, _)| (order(s), *sym));
+    let join = |items: Vec<String>| items.join(", ");
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "StateSet = {{{}}}",
+        join(states.iter().map(|s| s.to_string()).collect())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "SymbolSet = {{{}}}",
+        join(syms.iter().map(|s| s.to_string()).collect())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "TSymbolSet = {{{}}}",
+        join(tape_syms.iter().map(|s| s.to_string()).collect())
+    )
+    .unwrap();
+    out += "FnSet = {\n";
+    for (i, (s0, sym0, s1, sym1, dir)) in fns.iter().enumerate() {
+        let sep = if i + 1 == fns.len() { "" } else { "," };
+        writeln!(
+            out,
+            "    ({}, {}) -> ({}, {}, {}){}",
+            s0,
+            sym0,
+            s1,
+            sym1.unwrap_or(*sym0),
+            dir,
+            sep
+        )
+        .unwrap();
+    }
+    out += "}\n";
+    writeln!(out, "Start = {}", tm.start_state()).unwrap();
+    writeln!(
+        out,
+        "FinalSet = {{{}}}",
+        join(accept_states.iter().map(|s| s.to_string()).collect())
+    )
+    .unwrap();
+    writeln!(out, "Empty = {}", tm.empty_sym()).unwrap();
+    Ok(out)
+}
+
+/// Whether `s` matches `Ident` of the grammar.
+pub(crate) fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && s.len() > 1
+        && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+/// The `/* */` comments of a `.tm` file in order, [`to_tm`] and
+/// [`Document::to_tm`] do not keep them.
+pub fn comments(content: &str) -> Result<Vec<&str>, Error> {
+    // Comments are skipped between tokens, so they lie in the gaps between
+    // the innermost pairs, along with keywords and punctuation.
+    let mut gaps = Vec::new();
+    let mut end = 0;
+    for p in TMParser::parse(Rule::ROOT, content)?.flatten() {
+        if p.clone().into_inner().next().is_none() {
+            let span = p.as_span();
+            gaps.push(&content[end..span.start()]);
+            end = span.end();
+        }
+    }
+    gaps.push(&content[end..]);
+
+    let mut comments = Vec::new();
+    for mut gap in gaps {
+        while let Some(start) = gap.find("/*") {
+            let len = gap[start..].find("*/").map_or(gap.len() - start, |i| i + 2);
+            comments.push(&gap[start..start + len]);
+            gap = &gap[start + len..];
+        }
+    }
+    Ok(comments)
+}
+
+/// Parse a single transition, e.g. `(q0, a) -> (q1, x, R)`.
+pub fn parse_transfer_fn<T: AsRef<str>>(content: T) -> Result<TransferFnItem, Error> {
+    let mut pt = TMParser::parse(Rule::TRANSFER_FN, content.as_ref())?;
+    parse_fn(pt.next().unwrap())
+}
+
+fn parse_test(p: Pair<Rule>) -> Result<TestCase, Error> {
+    let mut p = p.into_inner();
+    let input = parse_word(p.next().unwrap());
+    let expect = p.next().unwrap();
+    let expect = match expect.as_rule() {
+        Rule::ExpectVerdict => Expect::Verdict(match expect.into_inner().as_str() {
+            "accept" => Verdict::Accept,
+            "reject" => Verdict::Reject,
+            _ => Verdict::Timeout,
+        }),
+        _ => Expect::Output(parse_word(expect.into_inner().next().unwrap())),
+    };
+    let mut test = TestCase::new(input, expect);
+    if let Some(steps) = p.next() {
+        let steps = steps
+            .as_str()
+            .parse()
+            .map_err(|_| Error::Semantic(format!("invalid step limit: {}", steps.as_str())))?;
+        test = test.max_steps(steps);
+    }
+    Ok(test)
+}
+
+/// Content of a quoted word, `\` escapes the next character.
+fn parse_word(p: Pair<Rule>) -> String {
+    let mut word = String::new();
+    let mut chars = p.into_inner().as_str().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => word.extend(chars.next()),
+            c => word.push(c),
+        }
+    }
+    word
+}
+
+fn quote(word: &str) -> String {
+    format!("\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
 fn parse_state_set(p: Pair<Rule>) -> FxHashSet<String> {
@@ -141,3 +376,82 @@ fn parse_symbol(p: Pair<Rule>) -> Symbol {
 #[derive(Parser)]
 #[grammar = "turing.pest"]
 struct TMParser;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMENTED: &str = r#"/* a^n b^n */
+StateSet = {q0, q1 /* unused */}
+SymbolSet = {a, /}
+FnSet = {(q0, a) -> (q1, /, R)}
+Start = q0
+FinalSet = {q1}
+Tests = {"a/*b*/" -> reject}
+/* trailing */"#;
+
+    #[test]
+    fn comments_are_found_outside_words() {
+        assert_eq!(
+            comments(COMMENTED).unwrap(),
+            vec!["/* a^n b^n */", "/* unused */", "/* trailing */"]
+        );
+    }
+
+    const TESTED: &str = r#"SymbolSet = {a}
+FnSet = {(q0, a) -> (q1, a, R)}
+FinalSet = {q1}
+Tests = {
+    "a" -> accept,
+    "a\"b" -> reject,
+    "" -> timeout within 3,
+    "aa" => "x\\y" within 50,
+}"#;
+
+    #[test]
+    fn parses_tests() {
+        let tests = parse_document(TESTED).unwrap().tests;
+        assert_eq!(
+            tests,
+            vec![
+                TestCase::new("a", Expect::Verdict(Verdict::Accept)),
+                TestCase::new("a\"b", Expect::Verdict(Verdict::Reject)),
+                TestCase::new("", Expect::Verdict(Verdict::Timeout)).max_steps(3),
+                TestCase::new("aa", Expect::Output("x\\y".to_string())).max_steps(50),
+            ]
+        );
+
+        let overflow = TESTED.replace("within 50", "within 99999999999999999999");
+        let e = parse_document(overflow).err().unwrap();
+        assert!(e.to_string().contains("invalid step limit"), "{}", e);
+    }
+
+    #[test]
+    fn tests_round_trip() {
+        let doc = parse_document(TESTED).unwrap();
+        let written = doc.to_tm().unwrap();
+        assert!(
+            written.ends_with(
+                r#"Tests = {
+    "a" -> accept,
+    "a\"b" -> reject,
+    "" -> timeout within 3,
+    "aa" => "x\\y" within 50
+}
+"#
+            ),
+            "{}",
+            written
+        );
+        let reparsed = parse_document(&written).unwrap();
+        assert_eq!(reparsed.tests, doc.tests);
+        assert_eq!(reparsed.to_tm().unwrap(), written);
+    }
+
+    #[test]
+    fn formatted_files_have_no_comments() {
+        let doc = parse_document(COMMENTED).unwrap();
+        assert!(comments(&doc.to_tm().unwrap()).unwrap().is_empty());
+        assert!(comments("").is_err());
+    }
+}
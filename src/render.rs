@@ -0,0 +1,361 @@
+//! Space-time diagrams of a run: one row per step, one column per tape cell.
+//!
+//! Cells are coloured by symbol, the empty symbol is white, and the cell
+//! under the head takes the colour of the current state.
+use crate::runner::Runner;
+use crate::tape::Tape;
+use crate::tm::{State, Symbol};
+
+use rustc_hash::FxHashMap;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// What part of a run to draw.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Steps to draw, a run ending earlier ends the diagram.
+    pub steps: Range<u64>,
+    /// Tape cells to draw, every visited cell if `None`.
+    pub window: Option<Range<i64>>,
+    /// Draw one step out of `stride`.
+    pub stride: u64,
+    /// Size of a cell in pixels.
+    pub scale: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            steps: 0..1000,
+            window: None,
+            stride: 1,
+            scale: 1,
+        }
+    }
+}
+
+struct Row {
+    leftmost: i64,
+    cells: Vec<Symbol>,
+    head: i64,
+    state: State,
+}
+
+/// Configurations recorded from a run.
+pub struct SpaceTime {
+    rows: Vec<Row>,
+    window: Range<i64>,
+    scale: usize,
+    empty_sym: Symbol,
+    sym_colors: FxHashMap<Symbol, [u8; 3]>,
+    state_colors: FxHashMap<State, [u8; 3]>,
+}
+
+impl SpaceTime {
+    /// Run a fed `runner` and record the steps selected by `options`.
+    pub fn record<T: Tape>(runner: &mut Runner<'_, T>, options: &Options) -> Self {
+        let stride = options.stride.max(1);
+        let mut rows = Vec::new();
+        runner.run(options.steps.start);
+        while runner.steps() < options.steps.end {
+            if runner.steps() >= options.steps.start
+                && (runner.steps() - options.steps.start).is_multiple_of(stride)
+            {
+                rows.push(capture(runner));
+            }
+            let steps = runner.steps();
+            runner.run(steps + 1);
+            if runner.steps() == steps {
+                break;
+            }
+        }
+
+        let window = options.window.clone().unwrap_or_else(|| {
+            let start = rows.iter().map(|r| r.leftmost).min().unwrap_or(0);
+            let end = rows
+                .iter()
+                .map(|r| r.leftmost + r.cells.len() as i64)
+                .max()
+                .unwrap_or(0);
+            start..end
+        });
+
+        let empty_sym = runner.tm().empty_sym();
+        let mut syms = rows
+            .iter()
+            .flat_map(|r| r.cells.iter().copied())
+            .filter(|s| *s != empty_sym)
+            .collect::<Vec<_>>();
+        syms.sort_unstable();
+        syms.dedup();
+        let mut states = rows
+            .iter()
+            .map(|r| State::clone(&r.state))
+            .collect::<Vec<_>>();
+        states.sort();
+        states.dedup();
+
+        let n = syms.len();
+        let sym_colors = syms
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| (s, color(i, n, 0.35, 0.95)))
+            .collect();
+        let n = states.len();
+        let state_colors = states
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| (s, color(i, n, 0.9, 0.75)))
+            .collect();
+
+        Self {
+            rows,
+            window,
+            scale: options.scale.max(1),
+            empty_sym,
+            sym_colors,
+            state_colors,
+        }
+    }
+
+    /// Size of the image in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        let width = (self.window.end - self.window.start).max(0) as usize;
+        (width * self.scale, self.rows.len() * self.scale)
+    }
+
+    /// Colour of each cell, row by row.
+    fn cells(&self) -> Vec<Vec<[u8; 3]>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.window
+                    .clone()
+                    .map(|pos| {
+                        if pos == row.head {
+                            return self.state_colors[&row.state];
+                        }
+                        let i = pos - row.leftmost;
+                        match row.cells.get(i as usize) {
+                            Some(s) if i >= 0 && *s != self.empty_sym => self.sym_colors[s],
+                            _ => [255, 255, 255],
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// RGB pixels, row by row.
+    fn pixels(&self) -> Vec<u8> {
+        let mut pixels = Vec::new();
+        for row in self.cells() {
+            let mut line = Vec::new();
+            for c in row {
+                for _ in 0..self.scale {
+                    line.extend_from_slice(&c);
+                }
+            }
+            for _ in 0..self.scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+        pixels
+    }
+
+    /// Write a binary PPM (`P6`) image.
+    pub fn write_ppm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let (width, height) = self.size();
+        write!(w, "P6\n{} {}\n255\n", width, height)?;
+        w.write_all(&self.pixels())
+    }
+
+    /// Write a PNG image, pixels are stored without compression. Fails with
+    /// [`InvalidInput`](io::ErrorKind::InvalidInput) for an empty diagram, a
+    /// PNG image has at least one pixel.
+    pub fn write_png<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let (width, height) = self.size();
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "nothing to draw, the steps or window are empty",
+            ));
+        }
+        let pixels = self.pixels();
+        let mut raw = Vec::with_capacity(pixels.len() + height);
+        for line in pixels.chunks(width * 3) {
+            raw.push(0); // no filter
+            raw.extend_from_slice(line);
+        }
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bit RGB
+
+        w.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_chunk(&mut w, b"IHDR", &ihdr)?;
+        write_chunk(&mut w, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(&mut w, b"IEND", &[])
+    }
+
+    /// Write an SVG image, a rectangle per run of same coloured cells.
+    pub fn write_svg<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let (width, height) = self.size();
+        let s = self.scale;
+        writeln!(
+            w,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\" shape-rendering=\"crispEdges\">",
+            width, height
+        )?;
+        writeln!(
+            w,
+            "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>",
+            width, height
+        )?;
+        for (y, row) in self.cells().into_iter().enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let c = row[x];
+                let len = row[x..].iter().take_while(|c1| **c1 == c).count();
+                if c != [255, 255, 255] {
+                    writeln!(
+                        w,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>",
+                        x * s,
+                        y * s,
+                        len * s,
+                        s,
+                        c[0],
+                        c[1],
+                        c[2]
+                    )?;
+                }
+                x += len;
+            }
+        }
+        writeln!(w, "</svg>")
+    }
+}
+
+fn capture<T: Tape>(runner: &Runner<'_, T>) -> Row {
+    let tape = runner.tape();
+    Row {
+        leftmost: tape.position() - tape.head() as i64,
+        cells: runner.ir().tape_str().chars().collect(),
+        head: tape.position(),
+        state: State::clone(runner.current_state()),
+    }
+}
+
+/// `i`-th of `n` colours evenly spread around the hue circle.
+fn color(i: usize, n: usize, saturation: f64, value: f64) -> [u8; 3] {
+    let h = i as f64 / n.max(1) as f64 * 6.0;
+    let c = value * saturation;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as usize {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = value - c;
+    let byte = |v: f64| ((v + m) * 255.0).round() as u8;
+    [byte(r), byte(g), byte(b)]
+}
+
+fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    w.write_all(&(data.len() as u32).to_be_bytes())?;
+    w.write_all(kind)?;
+    w.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    w.write_all(&crc.to_be_bytes())
+}
+
+fn crc32<'a, I: IntoIterator<Item = &'a u8>>(bytes: I) -> u32 {
+    let mut crc = !0u32;
+    for b in bytes {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn record(options: &Options) -> SpaceTime {
+        let tm = test_util::a_n_b_n();
+        let mut runner = Runner::with_tm(&tm);
+        runner.feed_str("aabb");
+        SpaceTime::record(&mut runner, options)
+    }
+
+    #[test]
+    fn png_has_the_size_of_the_diagram() {
+        let diagram = record(&Options {
+            scale: 2,
+            ..Options::default()
+        });
+        let (width, height) = diagram.size();
+        assert!(width > 0 && height > 0);
+        let mut png = Vec::new();
+        diagram.write_png(&mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png[16..20], (width as u32).to_be_bytes());
+        assert_eq!(png[20..24], (height as u32).to_be_bytes());
+    }
+
+    #[test]
+    fn empty_png_is_an_error() {
+        for options in [
+            Options {
+                steps: 0..0,
+                ..Options::default()
+            },
+            Options {
+                window: Some(3..3),
+                ..Options::default()
+            },
+        ] {
+            let e = record(&options).write_png(Vec::new()).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -1,17 +1,23 @@
-use crate::tm::{HeadDirection, State, Symbol, TM};
+use crate::tape::{Tape, VecTape};
+use crate::tm::{State, Symbol, TM};
 use std::fmt;
-use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
 
-pub struct Runner<'a> {
-    left_tape: Vec<Symbol>,
-    right_tape: Vec<Symbol>,
-    head: HeadPosition,
+/// Runs of at least this length are displayed as `sym^len`.
+const MIN_COLLAPSED_RUN: usize = 8;
+
+pub struct Runner<'a, T = VecTape> {
+    tape: T,
     current_state: State,
     tm: &'a TM,
     runner_state: RunnerState,
+    steps: u64,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RunnerState {
     Hungry,
     Running,
@@ -19,41 +25,72 @@ pub enum RunnerState {
     Reject,
 }
 
+/// How a run bounded by a step limit ended.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum HeadPosition {
-    Left(usize),
-    Right(usize),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verdict {
+    Accept,
+    Reject,
+    Timeout,
 }
 
-impl Default for HeadPosition {
-    fn default() -> Self {
-        HeadPosition::Right(0)
-    }
+/// Result of [`Runner::run`]: the verdict and the number of transitions taken.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunOutcome {
+    pub verdict: Verdict,
+    pub steps: u64,
+}
+
+/// Snapshot of a runner, `head` is the offset of the head in `tape`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    pub state: String,
+    pub tape: String,
+    pub head: usize,
+    pub steps: u64,
+    pub runner_state: RunnerState,
+}
+
+/// Configurations of a run, one before the first step and one after each step.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    pub input: String,
+    pub configs: Vec<Config>,
+    pub outcome: RunOutcome,
 }
 
 impl<'a> Runner<'a> {
     pub fn with_tm(tm: &'a TM) -> Self {
+        Self::with_tape(tm, VecTape::new(tm.empty_sym))
+    }
+}
+
+impl<'a, T: Tape> Runner<'a, T> {
+    /// Create a runner storing its tape in `tape`, e.g. a [`RleTape`](crate::RleTape).
+    pub fn with_tape(tm: &'a TM, tape: T) -> Self {
         Self {
-            left_tape: vec![tm.empty_sym],
-            right_tape: Vec::new(),
-            head: HeadPosition::default(),
-            current_state: Rc::clone(&tm.start_state),
+            tape,
+            current_state: Arc::clone(&tm.start_state),
             tm,
             runner_state: RunnerState::Hungry,
+            steps: 0,
         }
     }
 
     fn reset(&mut self) {
-        // just create a new runner, maybe optimize latter.
-        *self = Self::with_tm(self.tm);
+        self.current_state = Arc::clone(&self.tm.start_state);
+        self.runner_state = RunnerState::Hungry;
+        self.steps = 0;
     }
 
-    pub fn feed_str<T: AsRef<str>>(&mut self, input_str: T) {
+    pub fn feed_str<S: AsRef<str>>(&mut self, input_str: S) {
         if self.runner_state != RunnerState::Hungry {
             self.reset();
         }
-        self.right_tape = input_str.as_ref().chars().collect();
-        self.right_tape.push(self.tm.empty_sym);
+        self.tape.load(input_str.as_ref());
         self.runner_state = RunnerState::Running;
     }
 
@@ -66,66 +103,108 @@ impl<'a> Runner<'a> {
         }
     }
 
-    fn get_sym(&mut self) -> char {
-        let (tape, pos) = self.get_tape_pos_mut();
-        tape[pos]
+    /// Run until the machine halts or `limit` transitions have been taken.
+    ///
+    /// A machine that halts right after its `limit`-th transition is not
+    /// reported as a timeout.
+    pub fn run(&mut self, limit: u64) -> RunOutcome {
+        while self.step_within(limit) {}
+        self.outcome()
+    }
+
+    /// Feed `input_str` and run it like [`run`](Self::run), recording every
+    /// configuration on the way.
+    pub fn trace<S: AsRef<str>>(&mut self, input_str: S, limit: u64) -> Trace {
+        let input = input_str.as_ref().to_string();
+        self.feed_str(&input);
+        let mut configs = vec![self.config()];
+        while self.step_within(limit) {
+            configs.push(self.config());
+        }
+        // Rejecting takes no transition, the machine rejects in the last
+        // configuration.
+        if let Some(last) = configs.last_mut() {
+            last.runner_state = self.runner_state;
+        }
+        Trace {
+            input,
+            configs,
+            outcome: self.outcome(),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        Config {
+            state: self.current_state.to_string(),
+            tape: self.ir().tape_str(),
+            head: self.tape.head(),
+            steps: self.steps,
+            runner_state: self.runner_state,
+        }
     }
 
-    fn write_sym(&mut self, sym: Symbol) {
-        let (tape, pos) = self.get_tape_pos_mut();
-        tape[pos] = sym;
+    /// Take a step unless halted or out of steps, true if a transition fired.
+    fn step_within(&mut self, limit: u64) -> bool {
+        if self.runner_state != RunnerState::Running || (self.steps >= limit && self.has_transfer())
+        {
+            return false;
+        }
+        let steps = self.steps;
+        self.step();
+        self.steps != steps
     }
 
-    fn get_tape_pos_mut(&mut self) -> (&mut [Symbol], usize) {
-        let (tape, pos) = match self.head {
-            HeadPosition::Left(pos) => (&mut self.left_tape, pos),
-            HeadPosition::Right(pos) => (&mut self.right_tape, pos),
+    fn outcome(&self) -> RunOutcome {
+        let verdict = match self.runner_state {
+            RunnerState::Accept => Verdict::Accept,
+            RunnerState::Reject => Verdict::Reject,
+            _ => Verdict::Timeout,
         };
-        assert!(pos <= tape.len());
-        if pos == tape.len() {
-            tape.push(self.tm.empty_sym);
-        }
-        (tape, pos)
-    }
-
-    fn mv_head(&mut self, dir: HeadDirection) {
-        match &mut self.head {
-            HeadPosition::Left(pos) => match dir {
-                HeadDirection::Left => *pos += 1,
-                HeadDirection::Right => {
-                    if *pos == 0 {
-                        self.head = HeadPosition::Right(0);
-                    } else {
-                        *pos -= 1;
-                    }
-                }
-                HeadDirection::Stop => (),
-            },
-            HeadPosition::Right(pos) => match dir {
-                HeadDirection::Right => *pos += 1,
-                HeadDirection::Left => {
-                    if *pos == 0 {
-                        self.head = HeadPosition::Left(0);
-                    } else {
-                        *pos -= 1;
-                    }
-                }
-                HeadDirection::Stop => (),
-            },
+        RunOutcome {
+            verdict,
+            steps: self.steps,
         }
     }
 
+    pub fn tm(&self) -> &'a TM {
+        self.tm
+    }
+
+    pub fn tape(&self) -> &T {
+        &self.tape
+    }
+
+    pub fn current_state(&self) -> &State {
+        &self.current_state
+    }
+
+    pub fn runner_state(&self) -> RunnerState {
+        self.runner_state
+    }
+
+    /// Number of transitions taken since the last input was fed.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn has_transfer(&self) -> bool {
+        self.tm
+            .transfer(Arc::clone(&self.current_state), self.tape.read())
+            .is_some()
+    }
+
     fn do_transfer(&mut self) -> RunnerState {
-        let tape_sym = self.get_sym();
+        let tape_sym = self.tape.read();
         if let Some((next_state, next_sym, mv_dir)) = self.tm.transfer(
-            Rc::clone(&self.current_state), /*TODO fix this*/
+            Arc::clone(&self.current_state), /*TODO fix this*/
             tape_sym,
         ) {
             self.current_state = next_state;
+            self.steps += 1;
             if let Some(sym) = next_sym {
-                self.write_sym(sym);
+                self.tape.write(sym);
             }
-            self.mv_head(mv_dir);
+            self.tape.mv_head(mv_dir);
             if self.tm.accept(&self.current_state) {
                 self.runner_state = RunnerState::Accept;
             }
@@ -135,35 +214,63 @@ impl<'a> Runner<'a> {
         self.runner_state
     }
 
-    pub fn ir(&self) -> IR {
+    pub fn ir(&self) -> IR<'_> {
         IR {
-            head: self.head,
-            left_tape: &self.left_tape,
-            right_tape: &self.right_tape,
-            current_state: Rc::clone(&self.current_state),
+            tape: &self.tape,
+            current_state: Arc::clone(&self.current_state),
             runner_state: self.runner_state,
-            empty_sym: self.tm.empty_sym,
         }
     }
 }
 
+/// Run every input against `tm` on a pool of threads, one runner per
+/// thread. Outcomes are returned in input order.
+pub fn run_batch<S: AsRef<str> + Sync>(tm: &TM, inputs: &[S], limit: u64) -> Vec<RunOutcome> {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(inputs.len());
+    let next = AtomicUsize::new(0);
+    let mut outcomes = vec![None; inputs.len()];
+
+    thread::scope(|s| {
+        let handles = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut runner = Runner::with_tm(tm);
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= inputs.len() {
+                            break done;
+                        }
+                        runner.feed_str(&inputs[i]);
+                        done.push((i, runner.run(limit)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            for (i, outcome) in handle.join().unwrap() {
+                outcomes[i] = Some(outcome);
+            }
+        }
+    });
+    outcomes.into_iter().map(Option::unwrap).collect()
+}
+
 // TODO add more methods.
 pub struct IR<'a> {
-    head: HeadPosition,
-    left_tape: &'a [Symbol],
-    right_tape: &'a [Symbol],
+    tape: &'a dyn Tape,
     current_state: State,
-    empty_sym: Symbol,
     runner_state: RunnerState,
 }
 
 impl IR<'_> {
     pub fn tape_str(&self) -> String {
-        self.left_tape
-            .iter()
-            .copied()
-            .rev()
-            .chain(self.right_tape.iter().copied())
+        self.tape
+            .runs()
+            .into_iter()
+            .flat_map(|(sym, n)| std::iter::repeat_n(sym, n))
             .collect::<String>()
     }
 }
@@ -173,38 +280,74 @@ impl fmt::Display for IR<'_> {
         // write state first.
         writeln!(f, "{:?}", self.runner_state)?;
 
-        let syms = self
-            .left_tape
-            .iter()
-            .copied()
-            .rev()
-            .chain(self.right_tape.iter().copied())
-            .collect::<Vec<_>>();
-        let state_pos = match self.head {
-            HeadPosition::Left(pos) => {
-                let pos = self.left_tape.len() - pos;
-                if pos != 0 {
-                    pos - 1
-                } else {
-                    pos
-                }
-            }
-            HeadPosition::Right(pos) => self.left_tape.len() + pos,
-        };
-
-        for (pos, sym) in syms.iter().copied().enumerate() {
-            if pos == state_pos {
-                if pos == 0 {
-                    write!(f, "{}", self.empty_sym)?;
-                }
+        let head = self.tape.head();
+        let mut pos = 0;
+        for (sym, n) in self.tape.runs() {
+            if (pos..pos + n).contains(&head) {
+                write_run(f, sym, head - pos)?;
                 write!(f, "<{}>", self.current_state)?;
+                write_run(f, sym, pos + n - head)?;
+            } else {
+                write_run(f, sym, n)?;
             }
-            write!(f, "{}", sym)?;
+            pos += n;
         }
+        Ok(())
+    }
+}
 
-        if state_pos == syms.len() {
-            write!(f, "<{}>{}", self.current_state, self.empty_sym)?;
+fn write_run(f: &mut fmt::Formatter<'_>, sym: Symbol, n: usize) -> fmt::Result {
+    if n >= MIN_COLLAPSED_RUN {
+        write!(f, "{}^{}", sym, n)
+    } else {
+        (0..n).try_for_each(|_| write!(f, "{}", sym))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, verify::Words};
+
+    #[test]
+    fn run_batch_matches_sequential_runs() {
+        let tm = test_util::a_n_b_n();
+        let inputs = Words::new(vec!['a', 'b'], 8).collect::<Vec<_>>();
+        let sequential = |limit| {
+            let mut runner = Runner::with_tm(&tm);
+            inputs
+                .iter()
+                .map(|input| {
+                    runner.feed_str(input);
+                    runner.run(limit)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run_batch(&tm, &inputs, 1_000), sequential(1_000));
+        assert_eq!(run_batch(&tm, &inputs, 3), sequential(3));
+        assert!(run_batch::<&str>(&tm, &[], 1_000).is_empty());
+    }
+
+    #[test]
+    fn trace_ends_in_the_final_runner_state() {
+        let tm = test_util::a_n_b_n();
+        let mut runner = Runner::with_tm(&tm);
+        for (input, limit, state) in [
+            ("ab", 100, RunnerState::Accept),
+            ("aab", 100, RunnerState::Reject),
+            ("aabb", 3, RunnerState::Running),
+        ] {
+            let trace = runner.trace(input, limit);
+            assert_eq!(trace.configs.len() as u64, trace.outcome.steps + 1);
+            assert_eq!(
+                trace.configs.last().unwrap().runner_state,
+                state,
+                "{}",
+                input
+            );
+            assert!(trace.configs[..trace.configs.len() - 1]
+                .iter()
+                .all(|c| c.runner_state == RunnerState::Running));
         }
-        Ok(())
     }
 }
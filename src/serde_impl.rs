@@ -0,0 +1,244 @@
+//! `Serialize`/`Deserialize` for turing machines, enabled by the `serde` feature.
+//!
+//! A [`TM`] is written as below, sets are sorted to keep the output stable.
+//! `write` is `null` when the symbol under the head is kept. Deserializing
+//! goes through [`TMBuilder`], so invalid definitions are rejected, and so are
+//! two transitions on the same state and symbol.
+//!
+//! ```json
+//! {
+//!   "states": ["q0", "q1"],
+//!   "start_state": "q0",
+//!   "accept_states": ["q1"],
+//!   "syms": ["a"],
+//!   "tape_syms": ["B", "a"],
+//!   "empty_sym": "B",
+//!   "transfer_fns": [
+//!     {"state": "q0", "sym": "B", "next_state": "q1", "write": null, "dir": "Left"},
+//!     {"state": "q0", "sym": "a", "next_state": "q0", "write": null, "dir": "Right"}
+//!   ]
+//! }
+//! ```
+//!
+//! A [`TransferFn`] alone is the `transfer_fns` array above.
+use crate::tm::{HeadDirection, Symbol, TMBuilder, TransferFn, TransferFnItem, TM};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+#[derive(Serialize, Deserialize)]
+struct TransferFnDef {
+    state: String,
+    sym: Symbol,
+    next_state: String,
+    write: Option<Symbol>,
+    dir: HeadDirection,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TMDef {
+    states: Vec<String>,
+    start_state: String,
+    accept_states: Vec<String>,
+    syms: Vec<Symbol>,
+    tape_syms: Vec<Symbol>,
+    empty_sym: Symbol,
+    transfer_fns: Vec<TransferFnDef>,
+}
+
+fn sorted<T: Ord, I: IntoIterator<Item = T>>(items: I) -> Vec<T> {
+    let mut items = items.into_iter().collect::<Vec<_>>();
+    items.sort();
+    items
+}
+
+fn transfer_fn_defs(f: &TransferFn) -> Vec<TransferFnDef> {
+    let mut defs = f
+        .iter()
+        .map(|(s0, sym0, s1, sym1, dir)| TransferFnDef {
+            state: s0.to_string(),
+            sym: sym0,
+            next_state: s1.to_string(),
+            write: sym1,
+            dir,
+        })
+        .collect::<Vec<_>>();
+    defs.sort_by(|a, b| (&a.state, a.sym).cmp(&(&b.state, b.sym)));
+    defs
+}
+
+fn check_deterministic(defs: &[TransferFnDef]) -> Result<(), String> {
+    let mut seen = FxHashSet::default();
+    for def in defs {
+        if !seen.insert((&def.state, def.sym)) {
+            return Err(format!(
+                "Transfer fn has two transitions on ({}, {})",
+                def.state, def.sym
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Serialize for TransferFn {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        transfer_fn_defs(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TransferFn {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let defs = Vec::<TransferFnDef>::deserialize(deserializer)?;
+        check_deterministic(&defs).map_err(de::Error::custom)?;
+        let mut states = FxHashMap::<String, Arc<str>>::default();
+        let mut intern =
+            |s: String| Arc::clone(states.entry(s.clone()).or_insert_with(|| s.into()));
+        let mut map = FxHashMap::default();
+        for def in defs {
+            let from = (intern(def.state), def.sym);
+            map.insert(from, (intern(def.next_state), def.write, def.dir));
+        }
+        Ok(TransferFn(map))
+    }
+}
+
+impl Serialize for TM {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TMDef {
+            states: sorted(self.states().map(|s| s.to_string())),
+            start_state: self.start_state().to_string(),
+            accept_states: sorted(self.accept_states().map(|s| s.to_string())),
+            syms: sorted(self.syms()),
+            tape_syms: sorted(self.tape_syms()),
+            empty_sym: self.empty_sym(),
+            transfer_fns: transfer_fn_defs(self.transfer_fn()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TM {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let def = TMDef::deserialize(deserializer)?;
+        check_deterministic(&def.transfer_fns).map_err(de::Error::custom)?;
+        let fns = def.transfer_fns.into_iter().map(|f| {
+            TransferFnItem::new()
+                .from(f.state, f.sym)
+                .to(f.next_state, f.write, f.dir)
+        });
+        TMBuilder::new()
+            .states(def.states)
+            .start_state(def.start_state)
+            .accept_states(def.accept_states)
+            .syms(def.syms)
+            .tape_syms(def.tape_syms)
+            .empty_sym(def.empty_sym)
+            .transfer_fns(fns)
+            .build()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runner::{Runner, Trace, Verdict};
+    use crate::tm::{HeadDirection, TMBuilder, TransferFn, TransferFnItem, TM};
+    use serde_json::{json, Value};
+
+    fn tm() -> TM {
+        TMBuilder::new()
+            .states(vec!["q0", "q1"])
+            .start_state("q0")
+            .accept_state("q1")
+            .syms(vec!['a'])
+            .empty_sym('B')
+            .transfer_fns(vec![
+                TransferFnItem::new()
+                    .from("q0", 'a')
+                    .to("q0", None, HeadDirection::Right),
+                TransferFnItem::new()
+                    .from("q0", 'B')
+                    .to("q1", None, HeadDirection::Left),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    /// The machine of the module docs.
+    fn documented() -> Value {
+        json!({
+            "states": ["q0", "q1"],
+            "start_state": "q0",
+            "accept_states": ["q1"],
+            "syms": ["a"],
+            "tape_syms": ["B", "a"],
+            "empty_sym": "B",
+            "transfer_fns": [
+                {"state": "q0", "sym": "B", "next_state": "q1", "write": null, "dir": "Left"},
+                {"state": "q0", "sym": "a", "next_state": "q0", "write": null, "dir": "Right"}
+            ]
+        })
+    }
+
+    #[test]
+    fn tm_matches_documented_schema() {
+        assert_eq!(serde_json::to_value(tm()).unwrap(), documented());
+    }
+
+    #[test]
+    fn tm_round_trips() {
+        let tm: TM = serde_json::from_value(documented()).unwrap();
+        assert_eq!(serde_json::to_value(&tm).unwrap(), documented());
+        let mut runner = Runner::with_tm(&tm);
+        runner.feed_str("aa");
+        assert_eq!(runner.run(100).verdict, Verdict::Accept);
+    }
+
+    #[test]
+    fn transfer_fn_is_the_transfer_fns_array() {
+        let fns = documented()["transfer_fns"].clone();
+        let f: TransferFn = serde_json::from_value(fns.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&f).unwrap(), fns);
+    }
+
+    #[test]
+    fn trace_round_trips() {
+        let tm = tm();
+        let trace = Runner::with_tm(&tm).trace("aa", 100);
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(serde_json::from_str::<Trace>(&json).unwrap(), trace);
+    }
+
+    #[test]
+    fn rejects_invalid_machines() {
+        let mut unknown_state = documented();
+        unknown_state["transfer_fns"][0]["next_state"] = json!("q2");
+        assert!(serde_json::from_value::<TM>(unknown_state).is_err());
+
+        let mut unknown_dir = documented();
+        unknown_dir["transfer_fns"][0]["dir"] = json!("Up");
+        assert!(serde_json::from_value::<TM>(unknown_dir).is_err());
+
+        let mut long_sym = documented();
+        long_sym["syms"] = json!(["ab"]);
+        assert!(serde_json::from_value::<TM>(long_sym).is_err());
+
+        let mut missing_field = documented();
+        missing_field.as_object_mut().unwrap().remove("empty_sym");
+        assert!(serde_json::from_value::<TM>(missing_field).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_transitions() {
+        let mut tm = documented();
+        let mut fns = tm["transfer_fns"].as_array().unwrap().clone();
+        let mut duplicate = fns[1].clone();
+        duplicate["next_state"] = json!("q1");
+        fns.push(duplicate);
+        tm["transfer_fns"] = Value::Array(fns.clone());
+
+        let e = serde_json::from_value::<TM>(tm).err().unwrap();
+        assert!(e.to_string().contains("(q0, a)"), "{}", e);
+        assert!(serde_json::from_value::<TransferFn>(Value::Array(fns)).is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Building blocks for [`compose`](crate::compose), parameterised by the
+//! symbols they work on.
+//!
+//! A block is a run of symbols without the empty symbol. Unless stated
+//! otherwise a machine accepts once done, its start state is `q0` and its
+//! accept state `done`. Pre conditions are not checked: a machine started on
+//! another tape may reject, loop or leave anything on the tape.
+use crate::tm::{HeadDirection, Symbol, TMBuilder, TransferFnItem, TM};
+
+use rustc_hash::FxHashSet;
+use HeadDirection::{Left, Right, Stop};
+
+/// Symbols of blocks, each with a marked version at the same index in
+/// `marks`, and the empty symbol separating blocks.
+#[derive(Debug, Clone)]
+pub struct Alphabet {
+    syms: Vec<Symbol>,
+    marks: Vec<Symbol>,
+    empty: Symbol,
+}
+
+impl Alphabet {
+    /// E.g. `Alphabet::new("01", "xy", 'B')`. Fails if `marks` is not as long
+    /// as `syms` or the symbols are not all distinct.
+    pub fn new(syms: &str, marks: &str, empty: Symbol) -> Result<Self, String> {
+        let syms = syms.chars().collect::<Vec<_>>();
+        let marks = marks.chars().collect::<Vec<_>>();
+        if syms.len() != marks.len() {
+            return Err("Every symbol needs one marked symbol".to_string());
+        }
+        let all = syms
+            .iter()
+            .chain(marks.iter())
+            .chain(Some(&empty))
+            .collect::<FxHashSet<_>>();
+        if all.len() != 2 * syms.len() + 1 {
+            return Err("Symbols, marked symbols and the empty symbol must differ".to_string());
+        }
+        Ok(Self { syms, marks, empty })
+    }
+
+    fn block_syms(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.syms.iter().chain(self.marks.iter()).copied()
+    }
+}
+
+/// Move right to the first empty cell at or after the head.
+pub fn move_right_to_blank(a: &Alphabet) -> TM {
+    let mut fns = a
+        .block_syms()
+        .map(|sym| rule("q0", sym, "q0", None, Right))
+        .collect::<Vec<_>>();
+    fns.push(rule("q0", a.empty, DONE, None, Stop));
+    machine(a, fns)
+}
+
+/// Move left to the first empty cell at or before the head.
+pub fn move_left_to_blank(a: &Alphabet) -> TM {
+    mirror(&move_right_to_blank(a))
+}
+
+/// Pre: the head is on the first symbol of a block `w`.
+/// Post: `w` moved one cell right, the head on the empty cell left of it.
+pub fn shift_right(a: &Alphabet) -> TM {
+    let mut fns = vec![rule("q0", a.empty, DONE, None, Stop)];
+    for (i, x) in a.block_syms().enumerate() {
+        let carry = format!("carry{}", i);
+        fns.push(rule("q0", x, &carry, Some(a.empty), Right));
+        for (j, y) in a.block_syms().enumerate() {
+            fns.push(rule(&carry, y, &format!("carry{}", j), Some(x), Right));
+        }
+        fns.push(rule(&carry, a.empty, "back", Some(x), Left));
+    }
+    fns.extend(
+        a.block_syms()
+            .map(|sym| rule("back", sym, "back", None, Left)),
+    );
+    fns.push(rule("back", a.empty, DONE, None, Stop));
+    machine(a, fns)
+}
+
+/// Pre: the head is on the last symbol of a block `w`.
+/// Post: `w` moved one cell left, the head on the empty cell right of it.
+pub fn shift_left(a: &Alphabet) -> TM {
+    mirror(&shift_right(a))
+}
+
+/// Pre: the head is on the first symbol of an unmarked block `w`, with empty
+/// cells left of it and in the `|w| + 1` cells right of it.
+/// Post: `w B w`, `B` the empty symbol, the head on the first symbol of the
+/// first `w`.
+pub fn copy_block(a: &Alphabet) -> TM {
+    let mut fns = vec![rule("q0", a.empty, "unmark", None, Left)];
+    for (i, (x, mark)) in a.syms.iter().zip(a.marks.iter()).enumerate() {
+        let (carry, write) = (format!("carry{}", i), format!("write{}", i));
+        fns.push(rule("q0", *x, &carry, Some(*mark), Right));
+        fns.extend(a.syms.iter().map(|y| rule(&carry, *y, &carry, None, Right)));
+        fns.push(rule(&carry, a.empty, &write, None, Right));
+        fns.extend(a.syms.iter().map(|y| rule(&write, *y, &write, None, Right)));
+        fns.push(rule(&write, a.empty, "back", Some(*x), Left));
+        fns.push(rule("back", *x, "back", None, Left));
+        fns.push(rule("backw", *x, "backw", None, Left));
+        fns.push(rule("backw", *mark, "q0", None, Right));
+        fns.push(rule("unmark", *mark, "unmark", Some(*x), Left));
+    }
+    fns.push(rule("back", a.empty, "backw", None, Left));
+    fns.push(rule("unmark", a.empty, DONE, None, Right));
+    machine(a, fns)
+}
+
+/// Pre: the head is on the first symbol of `u B v B`, `u` and `v` unmarked
+/// blocks and `B` the empty symbol, with an empty cell left of `u`.
+/// Post: accepts if `u` equals `v` and rejects otherwise, in both cases with
+/// the tape unchanged and the head back on the first symbol of `u`. The
+/// reject state is `differ`.
+pub fn compare_blocks(a: &Alphabet) -> TM {
+    let mut fns = vec![rule("q0", a.empty, "end", None, Right)];
+    for (i, (x, mark)) in a.syms.iter().zip(a.marks.iter()).enumerate() {
+        let (carry, check) = (format!("carry{}", i), format!("check{}", i));
+        fns.push(rule("q0", *x, &carry, Some(*mark), Right));
+        fns.extend(a.block_syms().map(|y| rule(&carry, y, &carry, None, Right)));
+        fns.push(rule(&carry, a.empty, &check, None, Right));
+        fns.extend(
+            a.marks
+                .iter()
+                .map(|m| rule(&check, *m, &check, None, Right)),
+        );
+        fns.push(rule(&check, *x, "back", Some(*mark), Left));
+        for y in a.syms.iter().chain(Some(&a.empty)).filter(|y| *y != x) {
+            fns.push(rule(&check, *y, "fail", None, Stop));
+        }
+        fns.push(rule("backu", *x, "backu", None, Left));
+        fns.push(rule("backu", *mark, "q0", None, Right));
+        fns.push(rule("end", *x, "fail", None, Stop));
+        fns.push(rule("end", *mark, "end", None, Right));
+        for (unmark_v, unmark_u) in UNMARK.iter() {
+            fns.push(rule(unmark_v, *mark, unmark_v, Some(*x), Left));
+            fns.push(rule(unmark_v, *x, unmark_v, None, Left));
+            fns.push(rule(unmark_u, *mark, unmark_u, Some(*x), Left));
+            fns.push(rule(unmark_u, *x, unmark_u, None, Left));
+        }
+    }
+    for ((unmark_v, unmark_u), outcome) in UNMARK.iter().zip([DONE, "differ"]) {
+        fns.push(rule(unmark_v, a.empty, unmark_u, None, Left));
+        fns.push(rule(unmark_u, a.empty, outcome, None, Right));
+    }
+    fns.extend(a.block_syms().map(|y| rule("back", y, "back", None, Left)));
+    fns.push(rule("back", a.empty, "backu", None, Left));
+    fns.push(rule("end", a.empty, "unmarkv", None, Left));
+    fns.push(rule("fail", a.empty, "restorev", None, Left));
+    fns.extend(
+        a.block_syms()
+            .map(|y| rule("fail", y, "restorev", None, Left)),
+    );
+    machine(a, fns)
+}
+
+/// States of [`compare_blocks`] removing the marks from `v` then `u`, once
+/// the blocks are equal and once they differ.
+const UNMARK: [(&str, &str); 2] = [("unmarkv", "unmarku"), ("restorev", "restoreu")];
+
+/// Pre: the head is on the first symbol of `one^n`, or on an empty cell with
+/// an empty cell left of it for `n = 0`.
+/// Post: `one^(n+1)`, the head on its first symbol.
+pub fn unary_increment(one: Symbol, empty: Symbol) -> TM {
+    plain(
+        &[one],
+        empty,
+        vec![
+            rule("q0", one, "q0", None, Right),
+            rule("q0", empty, "back", Some(one), Left),
+            rule("back", one, "back", None, Left),
+            rule("back", empty, DONE, None, Right),
+        ],
+    )
+}
+
+/// Pre: the head is on the first symbol of `one^n`.
+/// Post: `one^(n-1)`, the head on its first symbol or the empty cell after
+/// it. Rejects with the tape unchanged if `n = 0`.
+pub fn unary_decrement(one: Symbol, empty: Symbol) -> TM {
+    plain(
+        &[one],
+        empty,
+        vec![rule("q0", one, DONE, Some(empty), Right)],
+    )
+}
+
+/// Pre: the head is on the most significant digit of a binary number written
+/// with `zero` and `one`, or on an empty cell for 0, with an empty cell left
+/// of it.
+/// Post: the number plus one, the head on its most significant digit.
+pub fn binary_increment(zero: Symbol, one: Symbol, empty: Symbol) -> TM {
+    let fns = vec![
+        rule("q0", zero, "q0", None, Right),
+        rule("q0", one, "q0", None, Right),
+        rule("q0", empty, "carry", None, Left),
+        rule("carry", one, "carry", Some(zero), Left),
+        rule("carry", zero, "back", Some(one), Left),
+        rule("carry", empty, DONE, Some(one), Stop),
+        rule("back", zero, "back", None, Left),
+        rule("back", one, "back", None, Left),
+        rule("back", empty, DONE, None, Right),
+    ];
+    plain(&[zero, one], empty, fns)
+}
+
+/// Pre: the head is on the first symbol of a block.
+/// Post: the block erased, the head on the empty cell right of where it was.
+pub fn erase_right(a: &Alphabet) -> TM {
+    let mut fns = a
+        .block_syms()
+        .map(|sym| rule("q0", sym, "q0", Some(a.empty), Right))
+        .collect::<Vec<_>>();
+    fns.push(rule("q0", a.empty, DONE, None, Stop));
+    machine(a, fns)
+}
+
+/// Pre: the head is on the last symbol of a block.
+/// Post: the block erased, the head on the empty cell left of where it was.
+pub fn erase_left(a: &Alphabet) -> TM {
+    mirror(&erase_right(a))
+}
+
+/// Pre: the head is on an unmarked symbol.
+/// Post: the symbol marked, the head unmoved. Rejects on other symbols.
+pub fn mark(a: &Alphabet) -> TM {
+    let fns = a
+        .syms
+        .iter()
+        .zip(a.marks.iter())
+        .map(|(x, m)| rule("q0", *x, DONE, Some(*m), Stop))
+        .collect();
+    machine(a, fns)
+}
+
+/// Pre: the head is on a marked symbol.
+/// Post: the symbol unmarked, the head unmoved. Rejects on other symbols.
+pub fn unmark(a: &Alphabet) -> TM {
+    let fns = a
+        .syms
+        .iter()
+        .zip(a.marks.iter())
+        .map(|(x, m)| rule("q0", *m, DONE, Some(*x), Stop))
+        .collect();
+    machine(a, fns)
+}
+
+const DONE: &str = "done";
+
+fn rule(
+    s0: &str,
+    sym0: Symbol,
+    s1: &str,
+    sym1: Option<Symbol>,
+    dir: HeadDirection,
+) -> TransferFnItem {
+    TransferFnItem::new().from(s0, sym0).to(s1, sym1, dir)
+}
+
+fn machine(a: &Alphabet, fns: Vec<TransferFnItem>) -> TM {
+    let states = fns
+        .iter()
+        .flat_map(|f| {
+            let (s0, s1) = f.states();
+            vec![s0.to_string(), s1.to_string()]
+        })
+        .collect::<Vec<_>>();
+    TMBuilder::new()
+        .states(states)
+        .syms(a.syms.iter().copied())
+        .tape_syms(a.marks.iter().copied())
+        .empty_sym(a.empty)
+        .start_state("q0")
+        .accept_state(DONE)
+        .transfer_fns(fns)
+        .build()
+        .expect("a stdlib machine is valid")
+}
+
+/// Machine over `syms` without marked symbols.
+fn plain(syms: &[Symbol], empty: Symbol, fns: Vec<TransferFnItem>) -> TM {
+    let a = Alphabet {
+        syms: syms.to_vec(),
+        marks: Vec::new(),
+        empty,
+    };
+    machine(&a, fns)
+}
+
+/// `tm` with left and right swapped.
+fn mirror(tm: &TM) -> TM {
+    TMBuilder::new()
+        .states(tm.states().map(|s| s.to_string()))
+        .start_state(tm.start_state().to_string())
+        .accept_states(tm.accept_states().map(|s| s.to_string()))
+        .syms(tm.syms())
+        .tape_syms(tm.tape_syms())
+        .empty_sym(tm.empty_sym())
+        .transfer_fns(tm.transfer_fns().map(|(s0, sym0, s1, sym1, dir)| {
+            let dir = match dir {
+                Left => Right,
+                Right => Left,
+                Stop => Stop,
+            };
+            rule(s0, sym0, s1, sym1, dir)
+        }))
+        .build()
+        .expect("a mirrored machine is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::seq;
+    use crate::tape::Tape;
+    use crate::test_util::run_on;
+    use crate::verify::Words;
+    use crate::{Runner, Verdict, Verdict::*};
+
+    fn alphabet() -> Alphabet {
+        Alphabet::new("01", "xy", 'B').unwrap()
+    }
+
+    /// Check the verdict, the tape without empty symbols on both ends, and
+    /// the head position relative to the first input symbol. Returns the
+    /// symbol under the head.
+    fn check(tm: &TM, input: &str, expected: (Verdict, &str, i64)) -> Symbol {
+        let mut runner = Runner::with_tm(tm);
+        let (verdict, _, tape) = run_on(&mut runner, input);
+        let head = runner.tape().position();
+        assert_eq!((verdict, tape.as_str(), head), expected, "on {:?}", input);
+        runner.tape().read()
+    }
+
+    /// `tm` started on the last symbol of the input block.
+    fn from_last(a: &Alphabet, tm: &TM) -> TM {
+        let step_left = machine(a, vec![rule("q0", a.empty, DONE, None, Left)]);
+        let to_last = seq(&move_right_to_blank(a), &step_left).unwrap();
+        seq(&to_last, tm).unwrap()
+    }
+
+    #[test]
+    fn block_machines() {
+        let a = alphabet();
+        let moved = check(&move_right_to_blank(&a), "0110", (Accept, "0110", 4));
+        assert_eq!(moved, 'B');
+        let shifted = check(&shift_right(&a), "0110", (Accept, "0110", 0));
+        assert_eq!(shifted, 'B');
+        let erased = check(&erase_right(&a), "0110", (Accept, "", 4));
+        assert_eq!(erased, 'B');
+        check(&mark(&a), "10", (Accept, "y0", 0));
+        check(&unmark(&a), "y0", (Accept, "10", 0));
+        check(&copy_block(&a), "011", (Accept, "011B011", 0));
+    }
+
+    #[test]
+    fn mirrored_block_machines() {
+        let a = alphabet();
+        let moved = check(&move_left_to_blank(&a), "0110", (Accept, "0110", -1));
+        assert_eq!(moved, 'B');
+        let moved = check(
+            &from_last(&a, &move_left_to_blank(&a)),
+            "0110",
+            (Accept, "0110", -1),
+        );
+        assert_eq!(moved, 'B');
+        let shifted = check(&from_last(&a, &shift_left(&a)), "0110", (Accept, "0110", 3));
+        assert_eq!(shifted, 'B');
+        let erased = check(&from_last(&a, &erase_left(&a)), "0110", (Accept, "", -1));
+        assert_eq!(erased, 'B');
+    }
+
+    #[test]
+    fn compare_blocks_of_every_pair() {
+        let compare = compare_blocks(&alphabet());
+        check(&compare, "011B0110", (Reject, "011B0110", 0));
+        let words = || Words::new(vec!['0', '1'], 3).filter(|w| !w.is_empty());
+        for u in words() {
+            for v in words() {
+                let input = format!("{}B{}", u, v);
+                let verdict = if u == v { Accept } else { Reject };
+                check(&compare, &input, (verdict, &input, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn counters() {
+        check(&unary_increment('1', 'B'), "111", (Accept, "1111", 0));
+        check(&unary_decrement('1', 'B'), "111", (Accept, "11", 1));
+        check(&unary_decrement('1', 'B'), "", (Reject, "", 0));
+        let increment = binary_increment('0', '1', 'B');
+        check(&increment, "1011", (Accept, "1100", 0));
+        check(&increment, "111", (Accept, "1000", -1));
+    }
+
+    #[test]
+    fn copy_equals_the_original() {
+        let a = alphabet();
+        let copy_then_compare = seq(&copy_block(&a), &compare_blocks(&a)).unwrap();
+        check(&copy_then_compare, "", (Accept, "", 0));
+        for w in Words::new(vec!['0', '1'], 5).skip(1) {
+            let copied = format!("{}B{}", w, w);
+            check(&copy_then_compare, &w, (Accept, &copied, 0));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::tm::{HeadDirection, Symbol};
+
+/// Storage of a turing machine's tape and head.
+///
+/// A tape covers every cell visited so far, the head always stays inside.
+pub trait Tape {
+    /// Replace content with `input` followed by an empty cell, one empty
+    /// cell is kept on the left, the head is on the first input symbol.
+    fn load(&mut self, input: &str);
+
+    fn read(&self) -> Symbol;
+
+    fn write(&mut self, sym: Symbol);
+
+    fn mv_head(&mut self, dir: HeadDirection);
+
+    /// Content from the leftmost to the rightmost cell, as runs of symbols.
+    fn runs(&self) -> Vec<(Symbol, usize)>;
+
+    /// Offset of the head from the leftmost cell.
+    fn head(&self) -> usize;
+
+    /// Position of the head, the first input symbol is at 0.
+    fn position(&self) -> i64;
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum HeadPosition {
+    Left(usize),
+    Right(usize),
+}
+
+impl Default for HeadPosition {
+    fn default() -> Self {
+        HeadPosition::Right(0)
+    }
+}
+
+/// Tape made of two vectors growing from cell 0 to both sides, one cell each.
+pub struct VecTape {
+    left_tape: Vec<Symbol>,
+    right_tape: Vec<Symbol>,
+    head: HeadPosition,
+    empty_sym: Symbol,
+}
+
+impl VecTape {
+    pub fn new(empty_sym: Symbol) -> Self {
+        Self {
+            left_tape: vec![empty_sym],
+            right_tape: vec![empty_sym],
+            head: HeadPosition::default(),
+            empty_sym,
+        }
+    }
+
+    fn get_tape_pos(&self) -> (&Vec<Symbol>, usize) {
+        match self.head {
+            HeadPosition::Left(pos) => (&self.left_tape, pos),
+            HeadPosition::Right(pos) => (&self.right_tape, pos),
+        }
+    }
+
+    fn get_tape_pos_mut(&mut self) -> (&mut Vec<Symbol>, usize) {
+        match self.head {
+            HeadPosition::Left(pos) => (&mut self.left_tape, pos),
+            HeadPosition::Right(pos) => (&mut self.right_tape, pos),
+        }
+    }
+}
+
+impl Tape for VecTape {
+    fn load(&mut self, input: &str) {
+        self.left_tape = vec![self.empty_sym];
+        self.right_tape = input.chars().collect();
+        self.right_tape.push(self.empty_sym);
+        self.head = HeadPosition::default();
+    }
+
+    fn read(&self) -> Symbol {
+        let (tape, pos) = self.get_tape_pos();
+        tape[pos]
+    }
+
+    fn write(&mut self, sym: Symbol) {
+        let (tape, pos) = self.get_tape_pos_mut();
+        tape[pos] = sym;
+    }
+
+    fn mv_head(&mut self, dir: HeadDirection) {
+        match &mut self.head {
+            HeadPosition::Left(pos) => match dir {
+                HeadDirection::Left => *pos += 1,
+                HeadDirection::Right => {
+                    if *pos == 0 {
+                        self.head = HeadPosition::Right(0);
+                    } else {
+                        *pos -= 1;
+                    }
+                }
+                HeadDirection::Stop => (),
+            },
+            HeadPosition::Right(pos) => match dir {
+                HeadDirection::Right => *pos += 1,
+                HeadDirection::Left => {
+                    if *pos == 0 {
+                        self.head = HeadPosition::Left(0);
+                    } else {
+                        *pos -= 1;
+                    }
+                }
+                HeadDirection::Stop => (),
+            },
+        }
+
+        let empty_sym = self.empty_sym;
+        let (tape, pos) = self.get_tape_pos_mut();
+        assert!(pos <= tape.len());
+        if pos == tape.len() {
+            tape.push(empty_sym);
+        }
+    }
+
+    fn runs(&self) -> Vec<(Symbol, usize)> {
+        let mut runs = Vec::new();
+        let syms = self
+            .left_tape
+            .iter()
+            .rev()
+            .chain(self.right_tape.iter())
+            .copied();
+        for sym in syms {
+            push_run(&mut runs, sym, 1);
+        }
+        runs
+    }
+
+    fn head(&self) -> usize {
+        match self.head {
+            HeadPosition::Left(pos) => self.left_tape.len() - pos - 1,
+            HeadPosition::Right(pos) => self.left_tape.len() + pos,
+        }
+    }
+
+    fn position(&self) -> i64 {
+        match self.head {
+            HeadPosition::Left(pos) => -(pos as i64) - 1,
+            HeadPosition::Right(pos) => pos as i64,
+        }
+    }
+}
+
+/// Run-length encoded tape, a run of identical symbols takes constant space.
+pub struct RleTape {
+    // Runs on each side of the head, the nearest run is the last.
+    left: Vec<(Symbol, usize)>,
+    right: Vec<(Symbol, usize)>,
+    current: Symbol,
+    position: i64,
+    empty_sym: Symbol,
+}
+
+impl RleTape {
+    pub fn new(empty_sym: Symbol) -> Self {
+        Self {
+            left: vec![(empty_sym, 1)],
+            right: vec![(empty_sym, 1)],
+            current: empty_sym,
+            position: 0,
+            empty_sym,
+        }
+    }
+}
+
+impl Tape for RleTape {
+    fn load(&mut self, input: &str) {
+        self.left = vec![(self.empty_sym, 1)];
+        self.right.clear();
+        let syms = input
+            .chars()
+            .chain(Some(self.empty_sym))
+            .collect::<Vec<_>>();
+        for sym in syms.into_iter().rev() {
+            push_run(&mut self.right, sym, 1);
+        }
+        self.current = pop_sym(&mut self.right, self.empty_sym);
+        self.position = 0;
+    }
+
+    fn read(&self) -> Symbol {
+        self.current
+    }
+
+    fn write(&mut self, sym: Symbol) {
+        self.current = sym;
+    }
+
+    fn mv_head(&mut self, dir: HeadDirection) {
+        let (from, to) = match dir {
+            HeadDirection::Left => (&mut self.left, &mut self.right),
+            HeadDirection::Right => (&mut self.right, &mut self.left),
+            HeadDirection::Stop => return,
+        };
+        self.position += if dir == HeadDirection::Left { -1 } else { 1 };
+        push_run(to, self.current, 1);
+        self.current = pop_sym(from, self.empty_sym);
+    }
+
+    fn runs(&self) -> Vec<(Symbol, usize)> {
+        let mut runs = self.left.clone();
+        push_run(&mut runs, self.current, 1);
+        for (sym, n) in self.right.iter().rev() {
+            push_run(&mut runs, *sym, *n);
+        }
+        runs
+    }
+
+    fn head(&self) -> usize {
+        self.left.iter().map(|(_, n)| n).sum()
+    }
+
+    fn position(&self) -> i64 {
+        self.position
+    }
+}
+
+fn push_run(runs: &mut Vec<(Symbol, usize)>, sym: Symbol, n: usize) {
+    match runs.last_mut() {
+        Some((last, m)) if *last == sym => *m += n,
+        _ => runs.push((sym, n)),
+    }
+}
+
+fn pop_sym(runs: &mut Vec<(Symbol, usize)>, empty_sym: Symbol) -> Symbol {
+    match runs.last_mut() {
+        Some((sym, n)) => {
+            let sym = *sym;
+            *n -= 1;
+            if *n == 0 {
+                runs.pop();
+            }
+            sym
+        }
+        None => empty_sym,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, products};
+    use crate::{tm_foo, verify::Words, Runner, TM};
+
+    /// Runs on a `RleTape` end like the ones on a `VecTape`, with the same
+    /// configuration displayed.
+    fn assert_tapes_agree(tm: &TM, inputs: &[String]) {
+        let mut vec = Runner::with_tm(tm);
+        let mut rle = Runner::with_tape(tm, RleTape::new(tm.empty_sym()));
+        for input in inputs {
+            vec.feed_str(input);
+            rle.feed_str(input);
+            assert_eq!(rle.run(10_000), vec.run(10_000), "{:?}", input);
+            assert_eq!(rle.ir().to_string(), vec.ir().to_string(), "{:?}", input);
+            assert_eq!(rle.tape().position(), vec.tape().position());
+        }
+    }
+
+    #[test]
+    fn rle_tape_runs_like_vec_tape() {
+        let inputs = Words::new(vec!['a', 'b'], 6).collect::<Vec<_>>();
+        assert_tapes_agree(&test_util::a_n_b_n(), &inputs);
+        assert_tapes_agree(&test_util::multiply(), &products());
+        assert_tapes_agree(&tm_foo(), &["a".repeat(5000)]);
+    }
+
+    #[test]
+    fn long_runs_are_collapsed() {
+        let tm = tm_foo();
+        let mut runner = Runner::with_tape(&tm, RleTape::new(tm.empty_sym()));
+        runner.feed_str("a".repeat(5000));
+        runner.run(10_000);
+        assert_eq!(runner.ir().to_string(), "Accept\nBa^4999<q1>aB");
+        runner.feed_str("aaaaaaa");
+        runner.run(10_000);
+        assert_eq!(runner.ir().to_string(), "Accept\nBaaaaaa<q1>aB");
+        runner.feed_str("aaaaaaaaa");
+        runner.run(10_000);
+        assert_eq!(runner.ir().to_string(), "Accept\nBa^8<q1>aB");
+    }
+}
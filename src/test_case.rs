@@ -0,0 +1,133 @@
+//! Expected results of a machine on given inputs, e.g. the `Tests` section of
+//! a `.tm` file.
+use crate::runner::{Config, RunOutcome, Runner, Verdict};
+use crate::tm::TM;
+
+/// Configurations kept at the end of a test run.
+pub const TRACE_TAIL: usize = 8;
+
+/// Expected result of a test case.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expect {
+    Verdict(Verdict),
+    /// The machine accepts, leaving this on the tape once empty symbols on
+    /// both ends are dropped.
+    Output(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestCase {
+    pub input: String,
+    pub expect: Expect,
+    /// Step limit of this case, overriding the one given to [`run`](Self::run).
+    pub max_steps: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestResult {
+    pub passed: bool,
+    pub outcome: RunOutcome,
+    /// Tape at the end of the run without empty symbols on both ends.
+    pub output: String,
+    /// Last [`TRACE_TAIL`] configurations of the run.
+    pub tail: Vec<Config>,
+}
+
+impl TestCase {
+    pub fn new<S: Into<String>>(input: S, expect: Expect) -> Self {
+        Self {
+            input: input.into(),
+            expect,
+            max_steps: None,
+        }
+    }
+
+    pub fn max_steps(mut self, max_steps: u64) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Run the case on `tm`, taking at most `max_steps` transitions unless
+    /// the case has its own limit.
+    pub fn run(&self, tm: &TM, max_steps: u64) -> TestResult {
+        let limit = self.max_steps.unwrap_or(max_steps);
+        let mut runner = Runner::with_tm(tm);
+        runner.feed_str(&self.input);
+        let mut tail = vec![runner.config()];
+        let outcome = loop {
+            let steps = runner.steps();
+            let outcome = runner.run((steps + 1).min(limit));
+            if runner.steps() != steps {
+                if tail.len() == TRACE_TAIL {
+                    tail.remove(0);
+                }
+                tail.push(runner.config());
+            }
+            if outcome.verdict != Verdict::Timeout || runner.steps() >= limit {
+                break outcome;
+            }
+        };
+
+        let empty = tm.empty_sym();
+        let output = runner.ir().tape_str().trim_matches(empty).to_string();
+        let passed = match &self.expect {
+            Expect::Verdict(verdict) => outcome.verdict == *verdict,
+            Expect::Output(expected) => outcome.verdict == Verdict::Accept && output == *expected,
+        };
+        TestResult {
+            passed,
+            outcome,
+            output,
+            tail,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tm_bar;
+
+    #[test]
+    fn passing_cases() {
+        let tm = tm_bar();
+        for case in [
+            TestCase::new("aabb", Expect::Verdict(Verdict::Accept)),
+            TestCase::new("aab", Expect::Verdict(Verdict::Reject)),
+            TestCase::new("aabb", Expect::Verdict(Verdict::Timeout)).max_steps(3),
+            TestCase::new("aabb", Expect::Output("xxyy".to_string())),
+        ] {
+            assert!(case.run(&tm, 100).passed, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn failing_case_keeps_the_tail() {
+        let tm = tm_bar();
+        let result = TestCase::new("aaabbb", Expect::Output("aaabbb".to_string())).run(&tm, 100);
+        assert!(!result.passed);
+        assert_eq!(result.outcome.verdict, Verdict::Accept);
+        assert!(result.outcome.steps > TRACE_TAIL as u64);
+        assert_eq!(result.output, "xxxyyy");
+        assert_eq!(result.tail.len(), TRACE_TAIL);
+
+        let mut runner = Runner::with_tm(&tm);
+        let trace = runner.trace("aaabbb", 100);
+        assert_eq!(
+            result.tail[..],
+            trace.configs[trace.configs.len() - TRACE_TAIL..]
+        );
+
+        let result = TestCase::new("aab", Expect::Verdict(Verdict::Accept)).run(&tm, 100);
+        assert!(!result.passed);
+        assert_eq!(result.outcome.verdict, Verdict::Reject);
+        assert_eq!(result.output, "xxy");
+
+        let result = TestCase::new("ab", Expect::Verdict(Verdict::Reject)).run(&tm, 100);
+        assert!(!result.passed);
+        assert_eq!(result.tail, runner.trace("ab", 100).configs);
+    }
+}
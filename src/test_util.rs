@@ -0,0 +1,52 @@
+//! Fixtures shared by the unit tests.
+use crate::tape::Tape;
+use crate::{parse, Runner, Verdict, TM};
+
+/// Step limit of [`run`], the fixtures halt well within it.
+pub const MAX_STEPS: u64 = 10_000;
+
+pub fn a_n_b_n() -> TM {
+    parse(include_str!("../examples/a_n_b_n.tm")).unwrap()
+}
+
+pub fn multiply() -> TM {
+    parse(include_str!("../examples/multiply")).unwrap()
+}
+
+/// Inputs `0^n 1 0^m 1` of [`multiply`] for `n, m < 4`, `$` standing for
+/// `n = 0`.
+pub fn products() -> Vec<String> {
+    let mut inputs = Vec::new();
+    for n in 0..4 {
+        for m in 0..4 {
+            let n = if n == 0 {
+                "$".to_string()
+            } else {
+                "0".repeat(n)
+            };
+            inputs.push(format!("{}1{}1", n, "0".repeat(m)));
+        }
+    }
+    inputs
+}
+
+/// Run `input` on `runner` for at most [`MAX_STEPS`] steps, returning the
+/// verdict, the steps and the tape without empty symbols on both ends.
+pub fn run_on<T: Tape>(runner: &mut Runner<T>, input: &str) -> (Verdict, u64, String) {
+    runner.feed_str(input);
+    let outcome = runner.run(MAX_STEPS);
+    let empty = runner.tm().empty_sym();
+    let tape = runner.ir().tape_str().trim_matches(empty).to_string();
+    (outcome.verdict, outcome.steps, tape)
+}
+
+/// [`run_on`] a new runner of `tm`.
+pub fn run(tm: &TM, input: &str) -> (Verdict, u64, String) {
+    run_on(&mut Runner::with_tm(tm), input)
+}
+
+/// [`run`] every input in turn.
+pub fn runs(tm: &TM, inputs: &[String]) -> Vec<(Verdict, u64, String)> {
+    let mut runner = Runner::with_tm(tm);
+    inputs.iter().map(|i| run_on(&mut runner, i)).collect()
+}
@@ -1,14 +1,15 @@
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
 use std::iter::IntoIterator;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// State of a turing machine, e.g. p0, p1.
-pub type State = Rc<str>;
+pub type State = Arc<str>;
 
 /// Symbol of a turing machine.
 pub type Symbol = char;
 
-/// Definition of a truing machine. Inmutable.
+/// Definition of a truing machine. Inmutable, can be shared between threads.
 #[allow(dead_code)]
 pub struct TM {
     states: FxHashSet<State>,
@@ -20,6 +21,12 @@ pub struct TM {
     transfer_fn: TransferFn,
 }
 
+// `run_batch` shares one machine between threads.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<TM>();
+};
+
 impl TM {
     pub fn transfer(
         &self,
@@ -32,18 +39,89 @@ impl TM {
     pub fn accept<T: AsRef<str>>(&self, s: T) -> bool {
         self.accept_states.contains(s.as_ref())
     }
+
+    pub fn start_state(&self) -> &State {
+        &self.start_state
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = &State> {
+        self.states.iter()
+    }
+
+    pub fn accept_states(&self) -> impl Iterator<Item = &State> {
+        self.accept_states.iter()
+    }
+
+    pub fn syms(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.syms.iter().copied()
+    }
+
+    pub fn tape_syms(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.tape_syms.iter().copied()
+    }
+
+    pub fn empty_sym(&self) -> Symbol {
+        self.empty_sym
+    }
+
+    /// Builder holding the definition of this machine, to derive new ones.
+    pub fn to_builder(&self) -> TMBuilder {
+        TMBuilder::new()
+            .states(self.states().map(|s| s.to_string()))
+            .start_state(self.start_state().to_string())
+            .accept_states(self.accept_states().map(|s| s.to_string()))
+            .syms(self.syms())
+            .tape_syms(self.tape_syms())
+            .empty_sym(self.empty_sym())
+            .transfer_fns(self.transfer_fns().map(|(s0, sym0, s1, sym1, dir)| {
+                TransferFnItem::new()
+                    .from(&s0[..], sym0)
+                    .to(&s1[..], sym1, dir)
+            }))
+    }
+
+    pub fn transfer_fn(&self) -> &TransferFn {
+        &self.transfer_fn
+    }
+
+    /// All items of the transfer function, as `(from, sym, to, write, dir)`.
+    pub fn transfer_fns(
+        &self,
+    ) -> impl Iterator<Item = (&State, Symbol, &State, Option<Symbol>, HeadDirection)> {
+        self.transfer_fn.iter()
+    }
 }
 
 /// Moving Direction of a turing machine's tape head.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeadDirection {
     Left,
     Right,
     Stop,
 }
 
+impl HeadDirection {
+    /// `L`, `R` or `S`, as written in `.tm` files.
+    pub fn letter(self) -> char {
+        match self {
+            HeadDirection::Left => 'L',
+            HeadDirection::Right => 'R',
+            HeadDirection::Stop => 'S',
+        }
+    }
+}
+
+impl fmt::Display for HeadDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.letter())
+    }
+}
+
 /// Tranfer function of a turing machine.
-pub struct TransferFn(FxHashMap<(State, Symbol), (State, Option<Symbol>, HeadDirection)>);
+pub struct TransferFn(
+    pub(crate) FxHashMap<(State, Symbol), (State, Option<Symbol>, HeadDirection)>,
+);
 
 impl TransferFn {
     pub fn transfer(
@@ -52,32 +130,50 @@ impl TransferFn {
         sym: Symbol,
     ) -> Option<(State, Option<Symbol>, HeadDirection)> {
         if let Some((s, sym, head)) = self.0.get(&(s, sym)) {
-            Some((Rc::clone(s), *sym, *head))
+            Some((Arc::clone(s), *sym, *head))
         } else {
             None
         }
     }
 
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&State, Symbol, &State, Option<Symbol>, HeadDirection)> {
+        self.0
+            .iter()
+            .map(|((s0, sym0), (s1, sym1, dir))| (s0, *sym0, s1, *sym1, *dir))
+    }
+
     pub fn tranfer_uncheck(&self, s: State, sym: Symbol) -> (State, Option<Symbol>, HeadDirection) {
         self.transfer(s, sym).unwrap()
     }
 
-    fn add_item(&mut self, item: TransferFnItem, states: &FxHashSet<State>) {
-        let (s0, sym0) = item.from.unwrap();
-        let (s1, sym1, dir) = item.to.unwrap();
-        let s0 = Rc::clone(states.get(&s0[..]).unwrap()); // TODO fix this
-        let s1 = Rc::clone(states.get(&s1[..]).unwrap()); // TODO fix this
+    fn add_item(&mut self, item: TransferFnItem, states: &FxHashSet<State>) -> Result<(), String> {
+        let (s0, sym0) = item.from.ok_or("Transfer fn item without source")?;
+        let (s1, sym1, dir) = item.to.ok_or("Transfer fn item without target")?;
+        let s0 = Arc::clone(
+            states
+                .get(&s0[..])
+                .ok_or_else(|| format!("Transfer fn state {} not in states set", s0))?,
+        );
+        let s1 = Arc::clone(
+            states
+                .get(&s1[..])
+                .ok_or_else(|| format!("Transfer fn state {} not in states set", s1))?,
+        );
         self.0.insert((s0, sym0), (s1, sym1, dir));
+        Ok(())
     }
 
     fn add_items<T: IntoIterator<Item = TransferFnItem>>(
         &mut self,
         items: T,
         states: &FxHashSet<State>,
-    ) {
+    ) -> Result<(), String> {
         for item in items {
-            self.add_item(item, states);
+            self.add_item(item, states)?;
         }
+        Ok(())
     }
 }
 
@@ -204,10 +300,10 @@ impl TMBuilder {
         let states = self
             .states
             .into_iter()
-            .map(Rc::from)
-            .collect::<FxHashSet<Rc<str>>>();
+            .map(Arc::from)
+            .collect::<FxHashSet<Arc<str>>>();
         let start_state = self.start_state.ok_or("No start state specified")?;
-        let start_state = Rc::clone(
+        let start_state = Arc::clone(
             states
                 .get(&start_state[..])
                 .ok_or("Start state not in states set")?,
@@ -217,18 +313,21 @@ impl TMBuilder {
             let s = states
                 .get(&accept_state[..])
                 .ok_or("Accept state not on states set")?;
-            accept_states.insert(Rc::clone(s));
+            accept_states.insert(Arc::clone(s));
         }
 
         let mut fns = TransferFn(FxHashMap::default());
-        fns.add_items(self.transfer_fn_items, &states);
+        fns.add_items(self.transfer_fn_items, &states)?;
+        let empty_sym = self.empty_sym.unwrap_or('B');
+        let mut tape_syms = self.tape_syms;
+        tape_syms.insert(empty_sym);
         Ok(TM {
             start_state,
             accept_states,
             states,
             syms: self.syms,
-            tape_syms: self.tape_syms,
-            empty_sym: self.empty_sym.unwrap_or('B'),
+            tape_syms,
+            empty_sym,
             transfer_fn: fns,
         })
     }
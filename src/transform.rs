@@ -0,0 +1,360 @@
+//! Transformations of machines that keep the verdict and step count of every
+//! run.
+use crate::tm::{HeadDirection, State, Symbol, TMBuilder, TransferFnItem, TM};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+/// What [`prune`] removed from a machine, each part sorted.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pruned {
+    pub states: Vec<String>,
+    /// Source state and symbol of the removed transitions.
+    pub transitions: Vec<(String, Symbol)>,
+    pub tape_syms: Vec<Symbol>,
+}
+
+impl Pruned {
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty() && self.transitions.is_empty() && self.tape_syms.is_empty()
+    }
+}
+
+/// Remove the states no run from the start state can enter, the transitions
+/// no run can take and the tape symbols the remaining transitions neither
+/// read nor write.
+///
+/// Runs halt on entering an accept state, so transitions out of accept states
+/// other than the start state are removed too. Input symbols and the empty
+/// symbol are kept.
+pub fn prune(tm: &TM) -> (TM, Pruned) {
+    let fires = |s: &State| !tm.accept(s) || s == tm.start_state();
+    let mut reachable = FxHashSet::default();
+    let mut todo = vec![tm.start_state()];
+    while let Some(s) = todo.pop() {
+        if reachable.insert(s) && fires(s) {
+            todo.extend(tm.transfer_fns().filter(|f| f.0 == s).map(|f| f.2));
+        }
+    }
+
+    let (kept, removed): (Vec<_>, Vec<_>) = tm
+        .transfer_fns()
+        .partition(|f| reachable.contains(f.0) && fires(f.0));
+    let mut used = tm.syms().collect::<FxHashSet<_>>();
+    used.insert(tm.empty_sym());
+    for (_, sym0, _, sym1, _) in kept.iter() {
+        used.insert(*sym0);
+        used.extend(*sym1);
+    }
+
+    let mut pruned = Pruned {
+        states: tm
+            .states()
+            .filter(|s| !reachable.contains(s))
+            .map(|s| s.to_string())
+            .collect(),
+        transitions: removed
+            .iter()
+            .map(|(s0, sym0, ..)| (s0.to_string(), *sym0))
+            .collect(),
+        tape_syms: tm.tape_syms().filter(|s| !used.contains(s)).collect(),
+    };
+    pruned.states.sort();
+    pruned.transitions.sort();
+    pruned.tape_syms.sort_unstable();
+
+    let tm = TMBuilder::new()
+        .states(reachable.iter().map(|s| s.to_string()))
+        .start_state(tm.start_state().to_string())
+        .accept_states(
+            tm.accept_states()
+                .filter(|s| reachable.contains(s))
+                .map(|s| s.to_string()),
+        )
+        .syms(tm.syms())
+        .tape_syms(used)
+        .empty_sym(tm.empty_sym())
+        .transfer_fns(
+            kept.into_iter()
+                .map(|(s0, sym0, s1, sym1, dir)| item(s0, sym0, s1, sym1, dir)),
+        )
+        .build()
+        .expect("a pruned machine is valid");
+    (tm, pruned)
+}
+
+/// Merge the states no run can tell apart, after [`prune`].
+///
+/// Blocks of states are refined until states of a block have transitions on
+/// the same symbols writing the same symbol, moving the same way and leading
+/// to the same block, like the minimisation of a DFA. Accept states other
+/// than the start state halt as soon as they are entered, so they all end up
+/// in one block. The merged state is named after the start state if it is in
+/// the block, else after the first state in name order.
+pub fn minimize(tm: &TM) -> TM {
+    let (tm, _) = prune(tm);
+    let mut states = tm.states().collect::<Vec<_>>();
+    states.sort();
+    let mut syms = tm.tape_syms().collect::<Vec<_>>();
+    syms.sort_unstable();
+    let halts = |s: &State| tm.accept(s) && s != tm.start_state();
+
+    let kind = |s: &State| match (tm.accept(s), s == tm.start_state()) {
+        (false, _) => 0,
+        (true, false) => 1,
+        (true, true) => 2,
+    };
+    let mut block = states
+        .iter()
+        .map(|s| (*s, kind(s)))
+        .collect::<FxHashMap<_, _>>();
+    let mut blocks = 0;
+    loop {
+        let mut ids = FxHashMap::default();
+        let refined = states
+            .iter()
+            .map(|s| {
+                let row = syms
+                    .iter()
+                    .filter(|_| !halts(s))
+                    .map(|sym| {
+                        tm.transfer(State::clone(s), *sym)
+                            .map(|(s1, sym1, dir)| (block[&s1], sym1.unwrap_or(*sym), dir as u8))
+                    })
+                    .collect::<Vec<_>>();
+                let n = ids.len();
+                (*s, *ids.entry((block[s], row)).or_insert(n))
+            })
+            .collect::<FxHashMap<_, _>>();
+        block = refined;
+        if ids.len() == blocks {
+            break;
+        }
+        blocks = ids.len();
+    }
+
+    let mut names = FxHashMap::default();
+    for s in states.iter() {
+        names.entry(block[s]).or_insert(*s);
+    }
+    names.insert(block[tm.start_state()], tm.start_state());
+    let name = |s: &State| names[&block[s]].to_string();
+    TMBuilder::new()
+        .states(names.values().map(|s| s.to_string()))
+        .start_state(tm.start_state().to_string())
+        .accept_states(tm.accept_states().map(name))
+        .syms(tm.syms())
+        .tape_syms(tm.tape_syms())
+        .empty_sym(tm.empty_sym())
+        .transfer_fns(
+            tm.transfer_fns()
+                .filter(|f| name(f.0) == **f.0)
+                .map(|(s0, sym0, s1, sym1, dir)| item(s0, sym0, &name(s1), sym1, dir)),
+        )
+        .build()
+        .expect("a minimized machine is valid")
+}
+
+/// Rename the states in `map`, the others keep their name. Fails if two
+/// states end up with the same name.
+pub fn rename_states(tm: &TM, map: &HashMap<String, String>) -> Result<TM, String> {
+    relabel(
+        tm,
+        |s| map.get(s).cloned().unwrap_or_else(|| s.to_string()),
+        |sym| sym,
+    )
+}
+
+/// Rename the symbols in `map` everywhere, including the empty symbol, the
+/// others are kept. Fails if two tape symbols end up the same.
+pub fn rename_symbols(tm: &TM, map: &HashMap<Symbol, Symbol>) -> Result<TM, String> {
+    relabel(tm, |s| s.to_string(), |sym| *map.get(&sym).unwrap_or(&sym))
+}
+
+/// Name the states `q0`, `q1`, ... in the order a breadth first search from
+/// the start state visits them, taking transitions in symbol order. States
+/// it does not visit come last, in name order.
+///
+/// Machines equal up to the names of their reachable states have the same
+/// canonical form, so the same [`to_tm`](crate::to_tm) text and
+/// [`canonical_hash`]. Run [`prune`] first to ignore unreachable states.
+pub fn canonicalize(tm: &TM) -> TM {
+    let mut fns = tm.transfer_fns().collect::<Vec<_>>();
+    fns.sort_by_key(|f| f.1);
+    let mut order = FxHashMap::default();
+    let mut todo = VecDeque::from(vec![tm.start_state()]);
+    while let Some(s) = todo.pop_front() {
+        if order.contains_key(&s[..]) {
+            continue;
+        }
+        order.insert(&s[..], order.len());
+        todo.extend(fns.iter().filter(|f| f.0 == s).map(|f| f.2));
+    }
+    let mut rest = tm
+        .states()
+        .filter(|s| !order.contains_key(&s[..]))
+        .collect::<Vec<_>>();
+    rest.sort();
+    for s in rest {
+        order.insert(&s[..], order.len());
+    }
+    relabel(tm, |s| format!("q{}", order[s]), |sym| sym).expect("canonical names are distinct")
+}
+
+/// Hash of the [`canonicalize`]d machine, equal for machines equal up to the
+/// names of their states, and stable across runs and platforms: it is the
+/// 64 bit FNV-1a hash of a text listing the sorted sets and transitions.
+pub fn canonical_hash(tm: &TM) -> u64 {
+    let tm = canonicalize(tm);
+    let sorted_states = |states: &mut dyn Iterator<Item = &State>| {
+        let mut states = states.map(|s| s.to_string()).collect::<Vec<_>>();
+        states.sort();
+        states.join(" ")
+    };
+    let sorted_syms = |syms: &mut dyn Iterator<Item = Symbol>| {
+        let mut syms = syms.collect::<Vec<_>>();
+        syms.sort_unstable();
+        syms.into_iter().collect::<String>()
+    };
+    let mut fns = tm
+        .transfer_fns()
+        .map(|(s0, sym0, s1, sym1, dir)| {
+            format!("{} {} {} {} {}", s0, sym0, s1, sym1.unwrap_or(sym0), dir)
+        })
+        .collect::<Vec<_>>();
+    fns.sort();
+
+    let mut text = String::new();
+    writeln!(text, "{}", sorted_states(&mut tm.states())).unwrap();
+    writeln!(text, "{}", tm.start_state()).unwrap();
+    writeln!(text, "{}", sorted_states(&mut tm.accept_states())).unwrap();
+    writeln!(text, "{}", sorted_syms(&mut tm.syms())).unwrap();
+    writeln!(text, "{}", sorted_syms(&mut tm.tape_syms())).unwrap();
+    writeln!(text, "{}", tm.empty_sym()).unwrap();
+    for f in fns {
+        writeln!(text, "{}", f).unwrap();
+    }
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Copy of `tm` with every state and symbol mapped, the maps must not merge
+/// states or tape symbols.
+fn relabel<F, G>(tm: &TM, state: F, sym: G) -> Result<TM, String>
+where
+    F: Fn(&str) -> String,
+    G: Fn(Symbol) -> Symbol,
+{
+    let states = tm.states().map(|s| state(s)).collect::<FxHashSet<_>>();
+    if states.len() != tm.states().count() {
+        return Err("Renaming merges states".to_string());
+    }
+    let tape_syms = tm.tape_syms().map(&sym).collect::<FxHashSet<_>>();
+    if tape_syms.len() != tm.tape_syms().count() {
+        return Err("Renaming merges symbols".to_string());
+    }
+    TMBuilder::new()
+        .states(states)
+        .start_state(state(tm.start_state()))
+        .accept_states(tm.accept_states().map(|s| state(s)))
+        .syms(tm.syms().map(&sym))
+        .tape_syms(tape_syms)
+        .empty_sym(sym(tm.empty_sym()))
+        .transfer_fns(tm.transfer_fns().map(|(s0, sym0, s1, sym1, dir)| {
+            item(&state(s0), sym(sym0), &state(s1), sym1.map(&sym), dir)
+        }))
+        .build()
+}
+
+fn item(
+    s0: &str,
+    sym0: Symbol,
+    s1: &str,
+    sym1: Option<Symbol>,
+    dir: HeadDirection,
+) -> TransferFnItem {
+    TransferFnItem::new().from(s0, sym0).to(s1, sym1, dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, products, runs};
+    use crate::{parse, Verdict};
+
+    const MULTIPLY: &str = include_str!("../examples/multiply");
+
+    #[test]
+    fn prune_removes_what_no_run_uses() {
+        let tm = parse(
+            "SymbolSet = {a}
+            TSymbolSet = {B, a, x, y, z}
+            FnSet = {
+                (q0, a) -> (q1, x, R),
+                (q1, B) -> (acc, B, L),
+                (acc, x) -> (q0, x, R),
+                (dead, a) -> (q1, z, R)
+            }
+            Start = q0
+            FinalSet = {acc}",
+        )
+        .unwrap();
+        let (pruned_tm, pruned) = prune(&tm);
+        assert_eq!(
+            pruned,
+            Pruned {
+                states: vec!["dead".to_string()],
+                transitions: vec![("acc".to_string(), 'x'), ("dead".to_string(), 'a')],
+                tape_syms: vec!['y', 'z'],
+            }
+        );
+        let inputs = ["", "a", "aa"].map(String::from);
+        assert_eq!(runs(&pruned_tm, &inputs), runs(&tm, &inputs));
+        assert!(prune(&pruned_tm).1.is_empty());
+    }
+
+    #[test]
+    fn multiply_is_already_minimal() {
+        let tm = test_util::multiply();
+        let min = minimize(&tm);
+        assert_eq!(min.states().count(), 13);
+        let expected = runs(&tm, &products());
+        assert_eq!(runs(&min, &products()), expected);
+        assert_eq!(
+            expected[2 * 4 + 3],
+            (Verdict::Accept, 99, "000000".to_string())
+        );
+    }
+
+    #[test]
+    fn minimize_merges_a_duplicated_state() {
+        // q13 does what q11 does.
+        let tm = parse(MULTIPLY.replace("q11, q12}", "q11, q12, q13}").replace(
+            "(q10, 1) -> (q11, B, R)",
+            "(q10, 1) -> (q13, B, R), (q13, 0) -> (q11, B, R), (q13, 1) -> (q12, B, R)",
+        ))
+        .unwrap();
+        assert_eq!(tm.states().count(), 14);
+        let min = minimize(&tm);
+        assert_eq!(min.states().count(), 13);
+        assert!(min.states().all(|s| &s[..] != "q13"));
+        assert_eq!(runs(&min, &products()), runs(&tm, &products()));
+    }
+
+    #[test]
+    fn canonical_hash_is_pinned_and_ignores_names() {
+        let tm = test_util::a_n_b_n();
+        assert_eq!(canonical_hash(&tm), 0xc51c_9331_8e7d_beff);
+        let names = tm
+            .states()
+            .map(|s| (s.to_string(), format!("x{}", s)))
+            .collect::<HashMap<_, _>>();
+        let renamed = rename_states(&tm, &names).unwrap();
+        assert_eq!(canonical_hash(&renamed), canonical_hash(&tm));
+        let multiply = test_util::multiply();
+        assert_ne!(canonical_hash(&multiply), canonical_hash(&tm));
+    }
+}
@@ -0,0 +1,288 @@
+//! Checking machines against a specification, or against each other, on
+//! every input up to a length.
+use crate::runner::{Runner, Verdict};
+use crate::tm::{Symbol, TM};
+
+/// How a machine disagrees with the expected answer on an input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mismatch {
+    /// Accepted an input that should be rejected.
+    FalseAccept,
+    /// Rejected an input that should be accepted.
+    FalseReject,
+    /// Did not halt within the step limit.
+    Timeout,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counterexample {
+    pub input: String,
+    pub mismatch: Mismatch,
+    pub steps: u64,
+}
+
+/// How two machines differ on an input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difference {
+    /// Both halted with different verdicts.
+    Verdict,
+    /// Both accepted, leaving different tapes.
+    Output,
+    /// At least one did not halt within the step limit, so the machines may
+    /// or may not agree.
+    Timeout,
+}
+
+/// Result of a machine on an input.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Behaviour {
+    pub verdict: Verdict,
+    pub steps: u64,
+    /// Tape at the end of the run without empty symbols on both ends.
+    pub output: String,
+}
+
+/// First input, in the order of [`Words`], two machines differ on.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Divergence {
+    pub input: String,
+    pub difference: Difference,
+    pub left: Behaviour,
+    pub right: Behaviour,
+}
+
+/// Words over `alphabet` of length `0..=max_len`, shortest first and in the
+/// order of `alphabet` within a length.
+pub struct Words {
+    alphabet: Vec<Symbol>,
+    max_len: usize,
+    /// Index in `alphabet` of each symbol of the next word.
+    next: Option<Vec<usize>>,
+}
+
+impl Words {
+    pub fn new(alphabet: Vec<Symbol>, max_len: usize) -> Self {
+        Self {
+            alphabet,
+            max_len,
+            next: Some(Vec::new()),
+        }
+    }
+}
+
+impl Iterator for Words {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let word = self.next.take()?;
+        let out = word.iter().map(|i| self.alphabet[*i]).collect();
+
+        let mut next = word;
+        let n = self.alphabet.len();
+        match next.iter().rposition(|i| i + 1 < n) {
+            Some(pos) => {
+                next[pos] += 1;
+                next[pos + 1..].iter_mut().for_each(|i| *i = 0);
+                self.next = Some(next);
+            }
+            None if next.len() < self.max_len && n > 0 => self.next = Some(vec![0; next.len() + 1]),
+            None => {}
+        }
+        Some(out)
+    }
+}
+
+/// Input alphabet of `tm` in symbol order, without the empty symbol.
+pub fn input_alphabet(tm: &TM) -> Vec<Symbol> {
+    let mut syms = tm
+        .syms()
+        .filter(|s| *s != tm.empty_sym())
+        .collect::<Vec<_>>();
+    syms.sort_unstable();
+    syms
+}
+
+/// Run `tm` on every word over its input alphabet up to `max_len` symbols,
+/// and report the inputs where its verdict differs from `expected`.
+///
+/// A machine running more than `step_limit` steps is reported as a
+/// [`Mismatch::Timeout`] whatever the expected answer.
+pub fn exhaustive<F>(tm: &TM, max_len: usize, step_limit: u64, expected: F) -> Vec<Counterexample>
+where
+    F: Fn(&str) -> bool,
+{
+    let mut runner = Runner::with_tm(tm);
+    let mut counterexamples = Vec::new();
+    for input in Words::new(input_alphabet(tm), max_len) {
+        runner.feed_str(&input);
+        let outcome = runner.run(step_limit);
+        let mismatch = match (outcome.verdict, expected(&input)) {
+            (Verdict::Timeout, _) => Mismatch::Timeout,
+            (Verdict::Accept, false) => Mismatch::FalseAccept,
+            (Verdict::Reject, true) => Mismatch::FalseReject,
+            _ => continue,
+        };
+        counterexamples.push(Counterexample {
+            input,
+            mismatch,
+            steps: outcome.steps,
+        });
+    }
+    counterexamples
+}
+
+/// Run `left` and `right` on every word over their input alphabets up to
+/// `max_len` symbols, and report the first input they give different
+/// verdicts on.
+///
+/// Inputs either machine runs more than `step_limit` steps on are skipped,
+/// the first of them is reported as a [`Difference::Timeout`] only if the
+/// machines agree on every other input.
+pub fn equivalent(left: &TM, right: &TM, max_len: usize, step_limit: u64) -> Option<Divergence> {
+    first_divergence(left, right, max_len, step_limit, false)
+}
+
+/// [`equivalent`] for transducers: inputs both machines accept must also
+/// leave the same tape, reported as a [`Difference::Output`] otherwise.
+pub fn equivalent_outputs(
+    left: &TM,
+    right: &TM,
+    max_len: usize,
+    step_limit: u64,
+) -> Option<Divergence> {
+    first_divergence(left, right, max_len, step_limit, true)
+}
+
+fn first_divergence(
+    left: &TM,
+    right: &TM,
+    max_len: usize,
+    step_limit: u64,
+    outputs: bool,
+) -> Option<Divergence> {
+    let mut alphabet = input_alphabet(left);
+    alphabet.extend(input_alphabet(right));
+    alphabet.sort_unstable();
+    alphabet.dedup();
+
+    let mut runners = (Runner::with_tm(left), Runner::with_tm(right));
+    let behave = |runner: &mut Runner, input: &str| {
+        runner.feed_str(input);
+        let outcome = runner.run(step_limit);
+        let empty = runner.tm().empty_sym();
+        Behaviour {
+            verdict: outcome.verdict,
+            steps: outcome.steps,
+            output: runner.ir().tape_str().trim_matches(empty).to_string(),
+        }
+    };
+    let mut timeout = None;
+    for input in Words::new(alphabet, max_len) {
+        let l = behave(&mut runners.0, &input);
+        let r = behave(&mut runners.1, &input);
+        let difference = match (l.verdict, r.verdict) {
+            (Verdict::Timeout, _) | (_, Verdict::Timeout) => Difference::Timeout,
+            (lv, rv) if lv != rv => Difference::Verdict,
+            (Verdict::Accept, _) if outputs && l.output != r.output => Difference::Output,
+            _ => continue,
+        };
+        let divergence = Divergence {
+            input,
+            difference,
+            left: l,
+            right: r,
+        };
+        if difference != Difference::Timeout {
+            return Some(divergence);
+        }
+        timeout.get_or_insert(divergence);
+    }
+    timeout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, test_util, tm_bar};
+
+    /// Loops on words starting with `b`, otherwise accepts `a^n` for `n` in
+    /// `accept`.
+    fn machine(accept: &[usize]) -> TM {
+        let mut fns = vec!["(q0, b) -> (q0, b, S)".to_string()];
+        for n in 0..3 {
+            fns.push(format!("(q{}, a) -> (q{}, a, R)", n, n + 1));
+        }
+        for n in accept {
+            fns.push(format!("(q{}, B) -> (acc, B, S)", n));
+        }
+        parse(format!(
+            "SymbolSet = {{a, b}}
+            FnSet = {{{}}}
+            Start = q0
+            FinalSet = {{acc}}",
+            fns.join(", ")
+        ))
+        .unwrap()
+    }
+
+    /// The language of [`tm_bar`], `a^n b^n` for `n > 0`.
+    fn a_n_b_n(input: &str) -> bool {
+        let n = input.len() / 2;
+        n > 0 && input == "a".repeat(n) + &"b".repeat(n)
+    }
+
+    #[test]
+    fn correct_machine_has_no_counterexamples() {
+        assert_eq!(exhaustive(&tm_bar(), 6, 1_000, a_n_b_n), vec![]);
+    }
+
+    #[test]
+    fn reports_every_mismatch() {
+        let counterexample = |input: &str, mismatch, steps| Counterexample {
+            input: input.to_string(),
+            mismatch,
+            steps,
+        };
+        let expected = |input: &str| input.is_empty() || input == "ab";
+        assert_eq!(
+            exhaustive(&tm_bar(), 4, 1_000, expected),
+            vec![
+                counterexample("", Mismatch::FalseReject, 0),
+                counterexample("aabb", Mismatch::FalseAccept, 13),
+            ]
+        );
+        assert_eq!(
+            exhaustive(&tm_bar(), 2, 4, a_n_b_n),
+            vec![counterexample("ab", Mismatch::Timeout, 4)]
+        );
+        assert_eq!(exhaustive(&tm_bar(), 2, 5, a_n_b_n), vec![]);
+    }
+
+    #[test]
+    fn finds_a_verdict_difference_after_timeouts() {
+        let d = equivalent(&machine(&[0, 1]), &machine(&[0, 1, 2]), 3, 100).unwrap();
+        assert_eq!(d.input, "aa");
+        assert_eq!(d.difference, Difference::Verdict);
+        assert_eq!(d.left.verdict, Verdict::Reject);
+        assert_eq!(d.right.verdict, Verdict::Accept);
+    }
+
+    #[test]
+    fn reports_a_timeout_only_without_a_difference() {
+        let d = equivalent(&machine(&[1]), &machine(&[1]), 3, 100).unwrap();
+        assert_eq!(d.input, "b");
+        assert_eq!(d.difference, Difference::Timeout);
+    }
+
+    #[test]
+    fn equal_machines_have_no_divergence() {
+        let tm = test_util::a_n_b_n();
+        assert_eq!(equivalent(&tm, &tm, 6, 1_000), None);
+        assert_eq!(equivalent_outputs(&tm, &tm, 6, 1_000), None);
+    }
+}